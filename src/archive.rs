@@ -0,0 +1,282 @@
+use std::fs;
+use std::io::{self, BufReader, Cursor, Read, Seek};
+use std::path::Path;
+
+use globset::GlobSet;
+
+use crate::decompress::Compression;
+use crate::file::File;
+use crate::i18n::{tr_with, warn};
+use crate::stats::format_bytes;
+
+/// Separator between an archive path and the path of a member inside it,
+/// e.g. `release.tar.gz!/docs/README.md`
+pub const MEMBER_SEPARATOR: &str = "!/";
+
+/// How many levels of archives nested in archives are opened by default
+pub const DEFAULT_MAX_ARCHIVE_DEPTH: usize = 3;
+
+/// Members larger than this are skipped by default, since each member is
+/// read into memory as a whole
+pub const DEFAULT_MAX_MEMBER_SIZE: u64 = 64 << 20;
+
+/// Number of leading bytes inspected to tell binary members from text
+const BINARY_SNIFF_LEN: usize = 8192;
+
+/// Archive formats understood by `--search-archives`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    /// Zip archives, including jar/war/ear files
+    Zip,
+    /// Tar archives, optionally compressed as a whole (`.tar.gz`, `.tgz`, ...)
+    Tar(Option<Compression>),
+}
+
+impl ArchiveKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        let has_suffix = |suffixes: &[&str]| suffixes.iter().any(|suffix| name.ends_with(suffix));
+
+        if has_suffix(&[".zip", ".jar", ".war", ".ear"]) {
+            Some(ArchiveKind::Zip)
+        } else if has_suffix(&[".tar"]) {
+            Some(ArchiveKind::Tar(None))
+        } else if has_suffix(&[".tar.gz", ".tgz"]) {
+            Some(ArchiveKind::Tar(Some(Compression::Gzip)))
+        } else if has_suffix(&[".tar.bz2", ".tbz2"]) {
+            Some(ArchiveKind::Tar(Some(Compression::Bzip2)))
+        } else if has_suffix(&[".tar.xz", ".txz"]) {
+            Some(ArchiveKind::Tar(Some(Compression::Xz)))
+        } else if has_suffix(&[".tar.zst", ".tzst"]) {
+            Some(ArchiveKind::Tar(Some(Compression::Zstd)))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ArchiveOptions {
+    /// Maximum number of nested archive levels to open
+    pub max_depth: usize,
+    /// Only search members whose path inside the archive matches
+    pub member_glob: Option<GlobSet>,
+    /// Skip members (including nested archives) larger than this many bytes
+    pub max_member_size: u64,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        ArchiveOptions {
+            max_depth: DEFAULT_MAX_ARCHIVE_DEPTH,
+            member_glob: None,
+            max_member_size: DEFAULT_MAX_MEMBER_SIZE,
+        }
+    }
+}
+
+impl ArchiveOptions {
+    /// Calls `visit` with every text member of the archive at `path`,
+    /// descending into nested archives up to `max_depth` levels.
+    pub fn for_each_member(&self, path: &Path, kind: ArchiveKind, visit: &mut dyn FnMut(File)) -> io::Result<()> {
+        let label = path.to_string_lossy().to_string();
+        match kind {
+            ArchiveKind::Zip => self.visit_zip(&label, BufReader::new(fs::File::open(path)?), 1, visit),
+            ArchiveKind::Tar(Some(compression)) => self.visit_tar(&label, compression.open(path)?, 1, visit),
+            ArchiveKind::Tar(None) => self.visit_tar(&label, BufReader::new(fs::File::open(path)?), 1, visit),
+        }
+    }
+
+    fn visit_zip<R: Read + Seek>(&self, label: &str, reader: R, depth: usize, visit: &mut dyn FnMut(File)) -> io::Result<()> {
+        let mut archive = zip::ZipArchive::new(reader).map_err(io::Error::other)?;
+        // 损坏的成员不影响其他成员，读完后再报告第一个错误
+        let mut first_error = None;
+        for index in 0..archive.len() {
+            let Ok(mut entry) = archive.by_index(index) else {
+                continue;
+            };
+            if !entry.is_file() || !self.wants_member(entry.name(), depth) {
+                continue;
+            }
+
+            let name = entry.name().to_string();
+            let size = entry.size();
+            match self.read_member(label, &name, size, &mut entry) {
+                Ok(Some(content)) => {
+                    drop(entry);
+                    self.visit_member(label, &name, content, depth, visit);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    fn visit_tar<R: Read>(&self, label: &str, reader: R, depth: usize, visit: &mut dyn FnMut(File)) -> io::Result<()> {
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            // 流式读取的 tar 在出错后无法继续
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let name = entry.path()?.to_string_lossy().to_string();
+            if !self.wants_member(&name, depth) {
+                continue;
+            }
+            let size = entry.size();
+            if let Some(content) = self.read_member(label, &name, size, &mut entry)? {
+                self.visit_member(label, &name, content, depth, visit);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a member is worth reading: a nested archive within the depth
+    /// limit, or a file accepted by the member glob.
+    fn wants_member(&self, name: &str, depth: usize) -> bool {
+        match ArchiveKind::from_name(name) {
+            Some(_) => depth < self.max_depth,
+            None => self.member_glob.as_ref().is_none_or(|glob| glob.is_match(name)),
+        }
+    }
+
+    /// Reads a member into memory, or warns and returns None when it is
+    /// larger than `max_member_size`. The size in the member header is
+    /// checked first, and the read itself stops just past the limit.
+    fn read_member(&self, label: &str, name: &str, size: u64, reader: impl Read) -> io::Result<Option<Vec<u8>>> {
+        let mut content = Vec::new();
+        if size <= self.max_member_size {
+            reader
+                .take(self.max_member_size.saturating_add(1))
+                .read_to_end(&mut content)
+                .map_err(|err| member_error(name, err))?;
+        }
+        if size > self.max_member_size || content.len() as u64 > self.max_member_size {
+            let member_label = format!("{}{}{}", label, MEMBER_SEPARATOR, name);
+            warn(&tr_with(
+                "member_too_large",
+                &[("path", &member_label), ("limit", &format_bytes(self.max_member_size))],
+            ));
+            return Ok(None);
+        }
+        Ok(Some(content))
+    }
+
+    fn visit_member(&self, label: &str, name: &str, content: Vec<u8>, depth: usize, visit: &mut dyn FnMut(File)) {
+        let member_label = format!("{}{}{}", label, MEMBER_SEPARATOR, name);
+
+        if let Some(kind) = ArchiveKind::from_name(name) {
+            // 嵌套归档中的错误只影响该归档本身
+            let _ = match kind {
+                ArchiveKind::Zip => self.visit_zip(&member_label, Cursor::new(content), depth + 1, visit),
+                ArchiveKind::Tar(Some(compression)) => compression
+                    .decoder(Cursor::new(content))
+                    .and_then(|reader| self.visit_tar(&member_label, reader, depth + 1, visit)),
+                ArchiveKind::Tar(None) => self.visit_tar(&member_label, Cursor::new(content), depth + 1, visit),
+            };
+            return;
+        }
+
+        // 跳过二进制成员（如 .class 文件）
+        if content[..content.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+            return;
+        }
+        if let Ok(text) = String::from_utf8(content) {
+            visit(File::new(member_label, text));
+        }
+    }
+}
+
+/// A read error of an archive member, naming the member
+fn member_error(name: &str, err: io::Error) -> io::Error {
+    io::Error::other(format!("{}: {}", name, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn zip_bytes(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in members {
+            writer.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn tar_bytes(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, content) in members {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *content).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn member_names(options: &ArchiveOptions, tar: Vec<u8>) -> Vec<String> {
+        let mut names = Vec::new();
+        options
+            .visit_tar("release.tar", Cursor::new(tar), 1, &mut |file| names.push(file.name))
+            .unwrap();
+        names
+    }
+
+    #[test]
+    fn test_nested_members() {
+        let inner = zip_bytes(&[("META-INF/MANIFEST.MF", b"Main-Class: App\n"), ("App.class", b"\xca\xfe\0\0")]);
+        let tar = tar_bytes(&[("docs/README.md", b"hello\n"), ("lib/app.jar", &inner)]);
+
+        let names = member_names(&ArchiveOptions::default(), tar.clone());
+        assert_eq!(
+            names,
+            vec!["release.tar!/docs/README.md", "release.tar!/lib/app.jar!/META-INF/MANIFEST.MF"]
+        );
+
+        let shallow = ArchiveOptions {
+            max_depth: 1,
+            ..Default::default()
+        };
+        assert_eq!(member_names(&shallow, tar), vec!["release.tar!/docs/README.md"]);
+    }
+
+    #[test]
+    fn test_member_glob() {
+        let mut glob = globset::GlobSetBuilder::new();
+        glob.add(globset::Glob::new("*.md").unwrap());
+        let options = ArchiveOptions {
+            member_glob: Some(glob.build().unwrap()),
+            ..Default::default()
+        };
+
+        let tar = tar_bytes(&[("docs/README.md", b"hello\n"), ("src/main.rs", b"fn main() {}\n")]);
+        assert_eq!(member_names(&options, tar), vec!["release.tar!/docs/README.md"]);
+    }
+
+    #[test]
+    fn test_max_member_size() {
+        let options = ArchiveOptions {
+            max_member_size: 8,
+            ..Default::default()
+        };
+        let large = "line\n".repeat(4);
+        let tar = tar_bytes(&[("small.txt", b"hello\n"), ("large.txt", large.as_bytes()), ("last.txt", b"bye\n")]);
+        assert_eq!(member_names(&options, tar), vec!["release.tar!/small.txt", "release.tar!/last.txt"]);
+
+        let zip = zip_bytes(&[("large.txt", large.as_bytes()), ("small.txt", b"hello\n")]);
+        let mut names = Vec::new();
+        options
+            .visit_zip("app.zip", Cursor::new(zip), 1, &mut |file| names.push(file.name))
+            .unwrap();
+        assert_eq!(names, vec!["app.zip!/small.txt"]);
+    }
+}
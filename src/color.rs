@@ -0,0 +1,300 @@
+use std::env;
+use std::fmt::Display;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+use owo_colors::{AnsiColors, DynColors, OwoColorize, Style};
+
+use crate::template::parse_color;
+
+/// When terminal output is colored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal, unless `NO_COLOR` is set or `CLICOLOR_FORCE` forces it
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(&self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                if env_flag("NO_COLOR") {
+                    false
+                } else if env_flag("CLICOLOR_FORCE") {
+                    true
+                } else {
+                    io::stdout().is_terminal() && env::var("TERM").map_or(true, |term| term != "dumb")
+                }
+            }
+        }
+    }
+}
+
+/// Whether an environment variable is set to something other than "" or "0"
+fn env_flag(name: &str) -> bool {
+    env::var_os(name).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Built-in palettes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Theme {
+    /// For dark terminal backgrounds
+    #[default]
+    Dark,
+    /// For light terminal backgrounds
+    Light,
+}
+
+/// The parts of the output that can be colored
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// File paths and names
+    Path,
+    /// Line numbers
+    Line,
+    /// Match columns
+    Column,
+    /// The matched text
+    Match,
+    /// The rest of the line in the default output
+    Text,
+}
+
+impl Role {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "path" => Some(Role::Path),
+            "line" => Some(Role::Line),
+            "column" => Some(Role::Column),
+            "match" => Some(Role::Match),
+            "text" => Some(Role::Text),
+            _ => None,
+        }
+    }
+}
+
+/// Colors and effects of one role
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RoleStyle {
+    pub fg: Option<DynColors>,
+    pub bg: Option<DynColors>,
+    pub bold: bool,
+    pub dimmed: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl RoleStyle {
+    fn fg(color: AnsiColors) -> Self {
+        RoleStyle {
+            fg: Some(DynColors::Ansi(color)),
+            ..Default::default()
+        }
+    }
+
+    fn style(&self) -> Style {
+        let mut style = Style::new();
+        if let Some(fg) = self.fg {
+            style = style.color(fg);
+        }
+        if let Some(bg) = self.bg {
+            style = style.on_color(bg);
+        }
+        if self.bold {
+            style = style.bold();
+        }
+        if self.dimmed {
+            style = style.dimmed();
+        }
+        if self.italic {
+            style = style.italic();
+        }
+        if self.underline {
+            style = style.underline();
+        }
+        style
+    }
+}
+
+/// One `--colors` entry: `ROLE:fg:COLOR`, `ROLE:bg:COLOR`,
+/// `ROLE:style:[no]EFFECT` or `ROLE:none`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorSpec {
+    role: Role,
+    change: Change,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Change {
+    Fg(DynColors),
+    Bg(DynColors),
+    Effect(String, bool),
+    Clear,
+}
+
+impl FromStr for ColorSpec {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = spec.trim().split(':').collect();
+        let role = Role::from_name(parts[0])
+            .ok_or_else(|| format!("unknown color role '{}' (expected path, line, column, match or text)", parts[0]))?;
+        let change = match parts[1..] {
+            ["none"] => Change::Clear,
+            ["fg", color] => Change::Fg(parse_color(color)?),
+            ["bg", color] => Change::Bg(parse_color(color)?),
+            ["style", effect] => {
+                let (name, on) = match effect.strip_prefix("no") {
+                    Some(name) => (name, false),
+                    None => (effect, true),
+                };
+                if !["bold", "dimmed", "italic", "underline"].contains(&name) {
+                    return Err(format!("unknown style '{}'", effect));
+                }
+                Change::Effect(name.to_string(), on)
+            }
+            _ => return Err(format!("invalid color spec '{}' (expected ROLE:fg|bg|style:VALUE or ROLE:none)", spec)),
+        };
+        Ok(ColorSpec { role, change })
+    }
+}
+
+/// The style of every role
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    path: RoleStyle,
+    line: RoleStyle,
+    column: RoleStyle,
+    matched: RoleStyle,
+    text: RoleStyle,
+}
+
+impl Palette {
+    pub fn new(theme: Theme) -> Self {
+        match theme {
+            Theme::Dark => Palette {
+                path: RoleStyle::fg(AnsiColors::Yellow),
+                line: RoleStyle::fg(AnsiColors::Green),
+                column: RoleStyle::fg(AnsiColors::Green),
+                matched: RoleStyle {
+                    bg: Some(DynColors::Ansi(AnsiColors::Red)),
+                    ..Default::default()
+                },
+                text: RoleStyle::fg(AnsiColors::Yellow),
+            },
+            Theme::Light => Palette {
+                path: RoleStyle::fg(AnsiColors::Magenta),
+                line: RoleStyle::fg(AnsiColors::Blue),
+                column: RoleStyle::fg(AnsiColors::Blue),
+                matched: RoleStyle {
+                    fg: Some(DynColors::Ansi(AnsiColors::Black)),
+                    bg: Some(DynColors::Ansi(AnsiColors::BrightYellow)),
+                    bold: true,
+                    ..Default::default()
+                },
+                text: RoleStyle::default(),
+            },
+        }
+    }
+
+    fn role(&self, role: Role) -> &RoleStyle {
+        match role {
+            Role::Path => &self.path,
+            Role::Line => &self.line,
+            Role::Column => &self.column,
+            Role::Match => &self.matched,
+            Role::Text => &self.text,
+        }
+    }
+
+    fn role_mut(&mut self, role: Role) -> &mut RoleStyle {
+        match role {
+            Role::Path => &mut self.path,
+            Role::Line => &mut self.line,
+            Role::Column => &mut self.column,
+            Role::Match => &mut self.matched,
+            Role::Text => &mut self.text,
+        }
+    }
+
+    /// Applies a `--colors` entry on top of the theme
+    pub fn apply(&mut self, spec: &ColorSpec) {
+        let style = self.role_mut(spec.role);
+        match &spec.change {
+            Change::Fg(color) => style.fg = Some(*color),
+            Change::Bg(color) => style.bg = Some(*color),
+            Change::Effect(name, on) => match name.as_str() {
+                "bold" => style.bold = *on,
+                "dimmed" => style.dimmed = *on,
+                "italic" => style.italic = *on,
+                _ => style.underline = *on,
+            },
+            Change::Clear => *style = RoleStyle::default(),
+        }
+    }
+}
+
+/// The palette of this run; `None` when colors are off
+static PALETTE: OnceLock<Option<Palette>> = OnceLock::new();
+
+/// Sets the palette of this run; `None` turns colors off. Until it is called
+/// (e.g. in tests) the dark theme is used.
+pub fn init(palette: Option<Palette>) {
+    let _ = PALETTE.set(palette);
+}
+
+fn active() -> Option<&'static Palette> {
+    PALETTE.get_or_init(|| Some(Palette::new(Theme::Dark))).as_ref()
+}
+
+/// Whether terminal output is colored in this run
+pub fn enabled() -> bool {
+    active().is_some()
+}
+
+/// Styles `text` for `role`, or returns it unchanged when colors are off
+pub fn paint(role: Role, text: impl Display) -> String {
+    match active() {
+        Some(palette) => text.style(palette.role(role).style()).to_string(),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_specs() {
+        let mut palette = Palette::new(Theme::Dark);
+        for spec in ["match:fg:black", "match:bg:yellow", "path:fg:magenta", "path:style:bold", "text:none"] {
+            palette.apply(&spec.parse().unwrap());
+        }
+        assert_eq!(
+            palette.matched,
+            RoleStyle {
+                fg: Some(DynColors::Ansi(AnsiColors::Black)),
+                bg: Some(DynColors::Ansi(AnsiColors::Yellow)),
+                ..Default::default()
+            }
+        );
+        assert_eq!(palette.path.fg, Some(DynColors::Ansi(AnsiColors::Magenta)));
+        assert!(palette.path.bold);
+        assert_eq!(palette.text, RoleStyle::default());
+
+        palette.apply(&"path:style:nobold".parse().unwrap());
+        assert!(!palette.path.bold);
+
+        assert!("title:fg:red".parse::<ColorSpec>().is_err());
+        assert!("match:fg:sparkly".parse::<ColorSpec>().is_err());
+        assert!("match:style:blink".parse::<ColorSpec>().is_err());
+        assert!("match:red".parse::<ColorSpec>().is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use xz2::read::XzDecoder;
+
+/// Compression formats understood by `--search-zip`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Detects the compression of a file from its extension, falling back to
+    /// the magic bytes at the start of `reader`. The peeked bytes are not
+    /// consumed, so the same reader can then be decompressed or read as text.
+    pub fn detect(path: &Path, reader: &mut impl BufRead) -> io::Result<Option<Self>> {
+        if let Some(compression) = Self::from_extension(path) {
+            return Ok(Some(compression));
+        }
+        Ok(Self::from_magic(reader.fill_buf()?))
+    }
+
+    fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gz" | "tgz" => Some(Compression::Gzip),
+            "bz2" | "tbz2" => Some(Compression::Bzip2),
+            "xz" | "txz" => Some(Compression::Xz),
+            "zst" | "zstd" | "tzst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    fn from_magic(magic: &[u8]) -> Option<Self> {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if magic.starts_with(b"BZh") {
+            Some(Compression::Bzip2)
+        } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Compression::Xz)
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// Wraps `reader` in a streaming decoder for this format, so the
+    /// decompressed content never has to be held in memory as a whole.
+    pub fn decoder<'a, R: Read + Send + 'a>(&self, reader: R) -> io::Result<Box<dyn BufRead + Send + 'a>> {
+        let decoder: Box<dyn Read + Send + 'a> = match self {
+            Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+            Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+            Compression::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+            Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(reader)?),
+        };
+        Ok(Box::new(BufReader::new(decoder)))
+    }
+
+    /// Opens a compressed file for streaming decompression
+    pub fn open(&self, path: &Path) -> io::Result<Box<dyn BufRead + Send>> {
+        self.decoder(BufReader::new(fs::File::open(path)?))
+    }
+}
+
+/// Rejects a decompressed stream whose first block holds a NUL byte or
+/// invalid UTF-8 with an `InvalidData` error, the error a plain binary file
+/// gives when read as text. A character cut off at the end of the block is
+/// not an error. The block stays buffered in `reader`.
+pub fn check_text(reader: &mut impl BufRead) -> io::Result<()> {
+    // A stream that cannot even be decompressed is corrupt, not binary
+    let block = reader.fill_buf().map_err(io::Error::other)?;
+    let invalid_utf8 = std::str::from_utf8(block).is_err_and(|err| err.error_len().is_some());
+    if block.contains(&0) || invalid_utf8 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "binary content"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn read_all(compression: Compression, compressed: Vec<u8>) -> String {
+        let mut content = String::new();
+        compression
+            .decoder(io::Cursor::new(compressed))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = "first line\nsecond line\n";
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(text.as_bytes()).unwrap();
+        let gz = gz.finish().unwrap();
+        assert_eq!(Compression::from_magic(&gz), Some(Compression::Gzip));
+        assert_eq!(read_all(Compression::Gzip, gz), text);
+
+        let mut bz = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bz.write_all(text.as_bytes()).unwrap();
+        let bz = bz.finish().unwrap();
+        assert_eq!(Compression::from_magic(&bz), Some(Compression::Bzip2));
+        assert_eq!(read_all(Compression::Bzip2, bz), text);
+
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+        xz.write_all(text.as_bytes()).unwrap();
+        let xz = xz.finish().unwrap();
+        assert_eq!(Compression::from_magic(&xz), Some(Compression::Xz));
+        assert_eq!(read_all(Compression::Xz, xz), text);
+
+        let zst = zstd::stream::encode_all(text.as_bytes(), 0).unwrap();
+        assert_eq!(Compression::from_magic(&zst), Some(Compression::Zstd));
+        assert_eq!(read_all(Compression::Zstd, zst), text);
+    }
+
+    #[test]
+    fn test_corrupt_stream() {
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all("line\n".repeat(1000).as_bytes()).unwrap();
+        let mut gz = gz.finish().unwrap();
+        gz.truncate(gz.len() / 2);
+
+        let reader = Compression::Gzip.decoder(io::Cursor::new(gz)).unwrap();
+        let mut file = crate::file::File::from_reader("app.log.gz".to_string(), reader);
+        while file.next_line().is_some() {}
+        assert!(file.take_error().is_some());
+    }
+
+    #[test]
+    fn test_binary_stream() {
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(b"\x7fELF\x02\x01\x01\x00\x00\x00").unwrap();
+        let mut reader = Compression::Gzip.decoder(io::Cursor::new(gz.finish().unwrap())).unwrap();
+        assert_eq!(check_text(&mut reader).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&[0xff, 0xfe, b'a', b'\n']).unwrap();
+        let mut reader = Compression::Gzip.decoder(io::Cursor::new(gz.finish().unwrap())).unwrap();
+        assert_eq!(check_text(&mut reader).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all("文本\n".as_bytes()).unwrap();
+        let mut reader = Compression::Gzip.decoder(io::Cursor::new(gz.finish().unwrap())).unwrap();
+        check_text(&mut reader).unwrap();
+        let mut content = String::new();
+        reader.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "文本\n");
+    }
+
+    #[test]
+    fn test_extension() {
+        assert_eq!(Compression::from_extension(Path::new("app.log.1.gz")), Some(Compression::Gzip));
+        assert_eq!(Compression::from_extension(Path::new("app.log.ZST")), Some(Compression::Zstd));
+        assert_eq!(Compression::from_extension(Path::new("app.log")), None);
+    }
+}
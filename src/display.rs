@@ -1,42 +1,151 @@
-use owo_colors::OwoColorize;
-use crate::format::LineFormatter;
+use crate::color::{Role, paint};
+use crate::i18n::tr_with;
+use crate::format::{LineFormatter, highlight_spans};
+use crate::options::{ColumnUnit, WrapOptions};
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Clone)]
 pub struct DisPlay {
-    query: String,
-    file_name: String,
     line_index: usize,
-    start_indexs: Vec<usize>,
+    /// Match spans as byte offsets into `line_content`
+    match_spans: Vec<(usize, usize)>,
+    /// The same spans as char offsets
+    char_spans: Vec<(usize, usize)>,
     line_content: String,
-    str: String,
+    column_unit: ColumnUnit,
+    /// Lines right before this one, oldest first, when context is captured
+    context_before: Vec<String>,
+    /// Lines right after this one
+    context_after: Vec<String>,
 }
 
 impl DisPlay {
-    pub fn new(query: String, file_name: String, line_index: usize, start_indexs: Vec<usize>, line_content: String, str: String) -> Self {
+    pub fn new(line_index: usize, match_spans: Vec<(usize, usize)>, line_content: String, column_unit: ColumnUnit) -> Self {
+        let char_spans = match_spans
+            .iter()
+            .map(|&(start, end)| {
+                let char_start = line_content[..start].chars().count();
+                (char_start, char_start + line_content[start..end].chars().count())
+            })
+            .collect();
         DisPlay {
-            query,
-            file_name,
             line_index,
-            start_indexs,
+            match_spans,
+            char_spans,
             line_content,
-            str
+            column_unit,
+            context_before: Vec::new(),
+            context_after: Vec::new(),
         }
     }
+
+    pub fn set_context_before(&mut self, lines: Vec<String>) {
+        self.context_before = lines;
+    }
+
+    pub fn push_context_after(&mut self, line: String) {
+        self.context_after.push(line);
+    }
+
+    /// Context lines before this line, oldest first
+    pub fn context_before(&self) -> &[String] {
+        &self.context_before
+    }
+
+    /// Context lines after this line
+    pub fn context_after(&self) -> &[String] {
+        &self.context_after
+    }
+
+    /// The full text of the matching line
+    pub fn line_content(&self) -> &str {
+        &self.line_content
+    }
+
+    /// Byte spans of every match occurrence in `line_content`
+    pub fn match_spans(&self) -> &[(usize, usize)] {
+        &self.match_spans
+    }
+
+    /// Number of match occurrences on this line
+    pub fn match_count(&self) -> usize {
+        self.match_spans.len()
+    }
+
+    /// 1-based line number of this line
+    pub fn line_number(&self) -> usize {
+        self.line_index + 1
+    }
+
+    /// 1-based first and last column of every match, in the configured unit
+    pub fn columns(&self) -> Vec<(usize, usize)> {
+        self.match_spans
+            .iter()
+            .map(|&(start, end)| {
+                let start_column = self.column_unit.measure(&self.line_content[..start]);
+                let end_column = start_column + self.column_unit.measure(&self.line_content[start..end]);
+                (start_column + 1, end_column)
+            })
+            .collect()
+    }
     
-    pub fn display(&self) -> String {
-        let formatter = LineFormatter::new();
-        
-        let formatted_content = formatter.format_long_line(
-            &self.line_content,
-            &self.query,
-            &self.start_indexs
-        );
+    /// `line:text` with the matches highlighted, as printed by --heading and
+    /// --no-heading. Only lines wider than --max-columns are shortened.
+    pub fn compact_display(&self, wrap: &WrapOptions, pure_text_output: bool) -> String {
+        let content = self.shown_content(wrap, pure_text_output, false);
+        if pure_text_output {
+            format!("{}:{}", self.line_number(), content)
+        } else {
+            format!("{}:{}", paint(Role::Line, self.line_number()), content)
+        }
+    }
+
+    /// The line as printed in the terminal output. Lines wider than
+    /// --max-columns are omitted, or previewed around the matches with
+    /// --max-columns-preview; with `fit_terminal` the other lines are cut
+    /// around the matches to the terminal width unless --no-truncate is set.
+    fn shown_content(&self, wrap: &WrapOptions, pure_text_output: bool, fit_terminal: bool) -> String {
+        let formatter = match wrap.max_columns {
+            Some(max_columns) if self.line_content.width() > max_columns => {
+                if !wrap.max_columns_preview {
+                    return tr_with("omitted_long_line", &[("count", &self.match_count())]);
+                }
+                LineFormatter::with_width(max_columns, wrap.context_chars)
+            }
+            _ if fit_terminal && !wrap.no_truncate => LineFormatter::with_context(wrap.context_chars),
+            _ if pure_text_output => return self.line_content.clone(),
+            _ => return highlight_spans(&self.line_content, &self.match_spans),
+        };
+        if pure_text_output {
+            formatter.format_long_line_pure(&self.line_content, &self.char_spans)
+        } else {
+            formatter.format_long_line(&self.line_content, &self.char_spans)
+        }
+    }
+
+    /// `line:column:text` for every match occurrence, as printed by --vimgrep.
+    /// An inverted line without occurrences is reported at column 1.
+    pub fn vimgrep_records(&self) -> Vec<String> {
+        let columns = self.columns();
+        let start_columns: Vec<usize> = if columns.is_empty() {
+            vec![1]
+        } else {
+            columns.iter().map(|&(start, _)| start).collect()
+        };
+        start_columns
+            .into_iter()
+            .map(|column| format!("{}:{}:{}", self.line_number(), column, self.line_content))
+            .collect()
+    }
+
+    pub fn display(&self, wrap: &WrapOptions) -> String {
+        let formatted_content = self.shown_content(wrap, false, true);
         
         let mut positions: String = String::new();
-        for start_index in &self.start_indexs {
+        for (start_column, end_column) in self.columns() {
             positions.push_str(&format!("{}-{}, ", 
-                (start_index + 1).green(), 
-                (start_index + self.query.len()).green()
+                paint(Role::Column, start_column), 
+                paint(Role::Column, end_column)
             ));
         }
         
@@ -45,28 +154,22 @@ impl DisPlay {
             positions.truncate(positions.len() - 2);
         }
         
-        format!(
-            "line number: {} position: [{}] line content: {}\n",
-            (self.line_index + 1).green(),
-            positions.blue(),
-            formatted_content.yellow()
-        )
+        let details = tr_with("line_details", &[
+            ("line", &paint(Role::Line, self.line_index + 1)),
+            ("positions", &positions),
+            ("content", &paint(Role::Text, formatted_content)),
+        ]);
+        format!("{}\n", details)
     }
 
-    pub fn pure_display(&self) -> String {
-        let formatter = LineFormatter::new();
-        
-        let formatted_content = formatter.format_long_line_pure(
-            &self.line_content,
-            &self.query,
-            &self.start_indexs
-        );
+    pub fn pure_display(&self, wrap: &WrapOptions) -> String {
+        let formatted_content = self.shown_content(wrap, true, true);
         
         let mut positions: String = String::new();
-        for start_index in &self.start_indexs {
+        for (start_column, end_column) in self.columns() {
             positions.push_str(&format!("{}-{}, ", 
-                (start_index + 1), 
-                (start_index + self.query.len())
+                start_column, 
+                end_column
             ));
         }
         
@@ -75,11 +178,55 @@ impl DisPlay {
             positions.truncate(positions.len() - 2);
         }
         
-        format!(
-            "line number: {} position: [{}] line content: {}\n",
-            (self.line_index + 1),
-            positions,
-            formatted_content
-        )
+        let details = tr_with("line_details", &[
+            ("line", &(self.line_index + 1)),
+            ("positions", &positions),
+            ("content", &formatted_content),
+        ]);
+        format!("{}\n", details)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::LineLayout;
+    use crate::report::sample_display;
+
+    #[test]
+    fn test_vimgrep_records() {
+        let display = sample_display(ColumnUnit::Char);
+        assert_eq!(display.vimgrep_records(), vec!["3:3:字 ab, ab", "3:7:字 ab, ab"]);
+
+        // Without --column-unit, vimgrep columns are bytes as Vim expects
+        let display = sample_display(LineLayout::Vimgrep.default_column_unit());
+        assert_eq!(display.vimgrep_records(), vec!["3:5:字 ab, ab", "3:9:字 ab, ab"]);
+        assert_eq!(display.compact_display(&WrapOptions::default(), true), "3:字 ab, ab");
+    }
+
+    #[test]
+    fn test_max_columns() {
+        let line = format!("{}needle{}", "x".repeat(40), "y".repeat(40));
+        let display = DisPlay::new(
+            0,
+            vec![(40, 46)],
+            line.clone(),
+            ColumnUnit::Char,
+        );
+        let mut wrap = WrapOptions {
+            max_columns: Some(30),
+            ..Default::default()
+        };
+        assert_eq!(display.compact_display(&wrap, true), "1:[Omitted long line with 1 matches]");
+
+        wrap.max_columns_preview = true;
+        wrap.context_chars = 4;
+        assert_eq!(display.shown_content(&wrap, true, false), "...xxxxneedleyyyy...");
+
+        let wrap = WrapOptions {
+            no_truncate: true,
+            ..Default::default()
+        };
+        assert_eq!(display.shown_content(&wrap, true, true), line);
     }
 }
@@ -0,0 +1,156 @@
+use std::process::Command;
+
+use anyhow::{Result, anyhow};
+use rayon::prelude::*;
+
+use crate::i18n::{tr, tr_with, warn};
+
+/// Placeholder replaced by the path of the current result
+pub const PATH_PLACEHOLDER: &str = "{}";
+/// Placeholder replaced by the line number of the first match
+pub const LINE_PLACEHOLDER: &str = "{line}";
+/// Placeholder replaced by the column of the first match
+pub const COLUMN_PLACEHOLDER: &str = "{col}";
+
+/// A file handed to `--exec`, with the location of its first match if any
+pub struct ExecTarget {
+    pub path: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl ExecTarget {
+    pub fn path(path: String) -> Self {
+        ExecTarget {
+            path,
+            line: None,
+            column: None,
+        }
+    }
+
+    fn expand(&self, arg: &str) -> String {
+        let number = |n: Option<usize>| n.map(|n| n.to_string()).unwrap_or_default();
+        arg.replace(LINE_PLACEHOLDER, &number(self.line))
+            .replace(COLUMN_PLACEHOLDER, &number(self.column))
+            .replace(PATH_PLACEHOLDER, &self.path)
+    }
+}
+
+fn has_placeholder(arg: &str) -> bool {
+    [PATH_PLACEHOLDER, LINE_PLACEHOLDER, COLUMN_PLACEHOLDER]
+        .iter()
+        .any(|placeholder| arg.contains(placeholder))
+}
+
+fn split_template(template: &[String]) -> Result<(&String, &[String])> {
+    template
+        .split_first()
+        .ok_or_else(|| anyhow!(tr("exec_requires_command")))
+}
+
+/// Builds the command for one result. Placeholders in the arguments are
+/// expanded; without any placeholder the path is appended.
+fn build_command(template: &[String], target: &ExecTarget) -> Result<Command> {
+    let (program, args) = split_template(template)?;
+
+    let mut command = Command::new(program);
+    command.args(args.iter().map(|arg| target.expand(arg)));
+    if !args.iter().any(|arg| has_placeholder(arg)) {
+        command.arg(&target.path);
+    }
+    Ok(command)
+}
+
+/// Runs the command once per target, with at most `jobs` commands running at
+/// the same time. Returns whether every command succeeded.
+pub fn exec_each(template: &[String], targets: &[ExecTarget], jobs: usize) -> Result<bool> {
+    let run = |target: &ExecTarget| -> Result<bool> {
+        let status = build_command(template, target)?
+            .status()
+            .map_err(|e| anyhow!(tr_with("cannot_run", &[("program", &template[0]), ("error", &e)])))?;
+        if !status.success() {
+            warn(&tr_with("command_failed_for", &[("path", &target.path), ("status", &status)]));
+        }
+        Ok(status.success())
+    };
+
+    let succeeded: Vec<bool> = if jobs <= 1 {
+        targets.iter().map(run).collect::<Result<_>>()?
+    } else {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+        pool.install(|| targets.par_iter().map(run).collect::<Result<_>>())?
+    };
+    Ok(succeeded.into_iter().all(|success| success))
+}
+
+/// Runs the command once with all paths. Every argument containing `{}` is
+/// repeated for each path; without any placeholder the paths are appended.
+/// Returns whether the command succeeded.
+pub fn exec_batch(template: &[String], paths: &[String]) -> Result<bool> {
+    if paths.is_empty() {
+        return Ok(true);
+    }
+    let (program, args) = split_template(template)?;
+
+    let mut command = Command::new(program);
+    let mut has_path_placeholder = false;
+    for arg in args {
+        if arg.contains(PATH_PLACEHOLDER) {
+            has_path_placeholder = true;
+            command.args(paths.iter().map(|path| arg.replace(PATH_PLACEHOLDER, path)));
+        } else {
+            command.arg(arg);
+        }
+    }
+    if !has_path_placeholder {
+        command.args(paths);
+    }
+
+    let status = command
+        .status()
+        .map_err(|e| anyhow!(tr_with("cannot_run", &[("program", &program), ("error", &e)])))?;
+    if !status.success() {
+        warn(&tr_with("command_failed", &[("status", &status)]));
+    }
+    Ok(status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholders() {
+        let target = ExecTarget {
+            path: "src/main.rs".to_string(),
+            line: Some(12),
+            column: Some(5),
+        };
+        let template: Vec<String> = ["vim", "+call cursor({line}, {col})", "{}"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let command = build_command(&template, &target).unwrap();
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["+call cursor(12, 5)", "src/main.rs"]);
+
+        let command = build_command(&["cat".to_string()], &target).unwrap();
+        assert_eq!(command.get_args().collect::<Vec<_>>(), ["src/main.rs"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_status() {
+        let template = |script: &str| -> Vec<String> {
+            ["sh", "-c", script, "sh"].iter().map(|s| s.to_string()).collect()
+        };
+        let targets = vec![ExecTarget::path("a".to_string()), ExecTarget::path("b".to_string())];
+        assert!(exec_each(&template("exit 0"), &targets, 1).unwrap());
+        assert!(!exec_each(&template("test \"$1\" = a"), &targets, 2).unwrap());
+        assert!(!exec_each(&template("kill -9 $$"), &targets, 1).unwrap());
+
+        let paths = vec!["a".to_string(), "b".to_string()];
+        assert!(exec_batch(&template("test $# = 2"), &paths).unwrap());
+        assert!(!exec_batch(&template("exit 3"), &paths).unwrap());
+    }
+}
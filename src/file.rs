@@ -1,45 +1,64 @@
+use std::io::{self, BufRead, Cursor};
+
 pub struct File {
     pub name: String,
-    format: Format,
-    raw_content: String,
-    line_count: usize,
-    line_content: Vec<String>,
-    line_index: usize,
-    line_offset: usize,
-}
-
-pub enum Format {
-    Text,
-    Binary,
+    reader: Box<dyn BufRead + Send>,
+    line_buffer: Vec<u8>,
+    line_content: String,
+    bytes_read: u64,
+    /// The error that ended reading early, e.g. a corrupt compressed stream
+    error: Option<io::Error>,
 }
 
 impl File {
-    pub fn new(name: String, format: Format, raw_content: String) -> Self {
-        let line_content: Vec<String> = raw_content.lines().map(|s| s.to_string()).collect();
-        let line_count = line_content.len();
+    pub fn new(name: String, raw_content: String) -> Self {
+        Self::from_reader(name, Box::new(Cursor::new(raw_content.into_bytes())))
+    }
+
+    /// Creates a file whose lines are read lazily from `reader`, e.g. a
+    /// decompression stream.
+    pub fn from_reader(name: String, reader: Box<dyn BufRead + Send>) -> Self {
         File {
             name,
-            format,
-            raw_content,
-            line_count,
-            line_content,
-            line_index: 0,
-            line_offset: 0,
+            reader,
+            line_buffer: Vec::new(),
+            line_content: String::new(),
+            bytes_read: 0,
+            error: None,
         }
     }
 
+    /// Reads the next line. `None` means the end of the file, or a read
+    /// error that `take_error` returns.
     pub fn next_line(&mut self) -> Option<&String> {
-        if self.line_index < self.line_count {
-            let line = &self.line_content[self.line_index];
-            self.line_index += 1;
-            Some(line)
-        } else {
-            None
+        self.line_buffer.clear();
+        match self.reader.read_until(b'\n', &mut self.line_buffer) {
+            Ok(0) => return None,
+            Ok(count) => self.bytes_read += count as u64,
+            Err(err) => {
+                self.error = Some(err);
+                return None;
+            }
         }
+
+        // 与 str::lines 一致，去掉行尾的 \n 或 \r\n
+        if self.line_buffer.last() == Some(&b'\n') {
+            self.line_buffer.pop();
+            if self.line_buffer.last() == Some(&b'\r') {
+                self.line_buffer.pop();
+            }
+        }
+        self.line_content = String::from_utf8_lossy(&self.line_buffer).into_owned();
+        Some(&self.line_content)
     }
 
-    pub fn line_index(&self) -> usize {
-        self.line_index
+    /// Number of bytes consumed from the reader so far
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
     }
-    
-}
\ No newline at end of file
+
+    /// The error that stopped `next_line` before the end of the file
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
@@ -0,0 +1,185 @@
+use std::fs::{self, Metadata};
+use std::num::{IntErrorKind, ParseIntError};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Result, anyhow};
+
+/// Size, age and ownership predicates evaluated on a file's metadata before
+/// it is read.
+#[derive(Clone, Default)]
+pub struct FileFilter {
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Only keep files modified after this instant
+    pub newer_than: Option<SystemTime>,
+    /// Only keep files modified before this instant
+    pub older_than: Option<SystemTime>,
+    /// Only keep files owned by this user id
+    pub owner: Option<u32>,
+    /// Only keep files having all of these permission bits set
+    pub permissions: Option<u32>,
+}
+
+impl FileFilter {
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        let size = metadata.len();
+        if self.min_size.is_some_and(|min| size < min) || self.max_size.is_some_and(|max| size > max) {
+            return false;
+        }
+
+        if self.newer_than.is_some() || self.older_than.is_some() {
+            let Ok(modified) = metadata.modified() else {
+                return false;
+            };
+            if self.newer_than.is_some_and(|t| modified <= t) || self.older_than.is_some_and(|t| modified >= t) {
+                return false;
+            }
+        }
+
+        self.matches_owner_and_permissions(metadata)
+    }
+
+    #[cfg(unix)]
+    fn matches_owner_and_permissions(&self, metadata: &Metadata) -> bool {
+        use std::os::unix::fs::MetadataExt;
+
+        if self.owner.is_some_and(|uid| metadata.uid() != uid) {
+            return false;
+        }
+        self.permissions.is_none_or(|bits| metadata.mode() & bits == bits)
+    }
+
+    #[cfg(not(unix))]
+    fn matches_owner_and_permissions(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+}
+
+/// Parses a file size such as `512`, `10K`, `1.5M` or `2G` (binary units).
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((i, unit)) if unit.is_ascii_alphabetic() => {
+            let multiplier: u64 = match unit.to_ascii_uppercase() {
+                'B' => 1,
+                'K' => 1 << 10,
+                'M' => 1 << 20,
+                'G' => 1 << 30,
+                'T' => 1 << 40,
+                _ => return Err(anyhow!("Unknown size suffix '{}' (expected K, M, G or T)", unit)),
+            };
+            (&value[..i], multiplier)
+        }
+        _ => (value, 1),
+    };
+
+    // Only plain decimals: f64 would also accept "1e9", "inf" and "NaN"
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return Err(anyhow!("Invalid size '{}'", value));
+    }
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid size '{}'", value))?;
+    let bytes = number * multiplier as f64;
+    if bytes >= u64::MAX as f64 {
+        return Err(anyhow!("Size '{}' is too large", value));
+    }
+    Ok(bytes as u64)
+}
+
+/// Parses a duration such as `30s`, `15m`, `2h`, `7d` or `2w`.
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("Missing unit in duration '{}' (expected s, m, h, d or w)", value))?;
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().map_err(|err: ParseIntError| match err.kind() {
+        IntErrorKind::PosOverflow => anyhow!("Duration '{}' is too large", value),
+        _ => anyhow!("Invalid duration '{}'", value),
+    })?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(anyhow!("Unknown duration unit '{}' (expected s, m, h, d or w)", unit)),
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| anyhow!("Duration '{}' is too large", value))
+}
+
+/// Parses the argument of `--newer-than`/`--older-than`: either a duration
+/// before now or the path of a reference file whose modification time is used.
+pub fn parse_time_bound(value: &str) -> Result<SystemTime> {
+    match parse_duration(value) {
+        Ok(duration) => {
+            return SystemTime::now()
+                .checked_sub(duration)
+                .ok_or_else(|| anyhow!("Duration '{}' is too large", value));
+        }
+        // A leading digit means a duration unless such a file exists, so
+        // report why the duration was rejected
+        Err(err) if value.starts_with(|c: char| c.is_ascii_digit()) && fs::metadata(value).is_err() => {
+            return Err(err);
+        }
+        Err(_) => {}
+    }
+
+    let metadata = fs::metadata(value)
+        .map_err(|_| anyhow!("'{}' is neither a duration (e.g. 2h, 7d) nor an existing file", value))?;
+    Ok(metadata.modified()?)
+}
+
+/// Parses an octal permission mask such as `644` or `0755`.
+pub fn parse_permissions(value: &str) -> Result<u32> {
+    u32::from_str_radix(value.trim_start_matches("0o"), 8)
+        .map_err(|_| anyhow!("Invalid octal permission mask '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("10K").unwrap(), 10 * 1024);
+        assert_eq!(parse_size("1.5m").unwrap(), 1536 * 1024);
+        assert_eq!(parse_size("2G").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("big").is_err());
+    }
+
+    #[test]
+    fn test_parse_size_rejects_overflow() {
+        assert!(parse_size("1e3").is_err());
+        assert!(parse_size("inf").is_err());
+        assert!(parse_size("NaN").is_err());
+        assert!(parse_size("-1K").is_err());
+        assert!(parse_size("16777216T").unwrap_err().to_string().contains("too large"));
+        assert!(parse_size("99999999999999999999").unwrap_err().to_string().contains("too large"));
+        assert_eq!(parse_size("16777215T").unwrap(), 16777215 << 40);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(2 * 3600));
+        assert_eq!(parse_duration("7d").unwrap(), Duration::from_secs(7 * 86400));
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("7y").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration_rejects_overflow() {
+        let err = parse_duration("30500568904944w").unwrap_err();
+        assert_eq!(err.to_string(), "Duration '30500568904944w' is too large");
+        let err = parse_duration("99999999999999999999s").unwrap_err();
+        assert_eq!(err.to_string(), "Duration '99999999999999999999s' is too large");
+        assert!(parse_time_bound("30500568904944w").unwrap_err().to_string().contains("too large"));
+    }
+}
@@ -1,5 +1,22 @@
-use owo_colors::OwoColorize;
+use crate::color::{Role, paint};
 use std::env;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use unicode_width::UnicodeWidthChar;
+
+/// 匹配内容前后默认保留的字符数
+pub const DEFAULT_CONTEXT_CHARS: usize = 20;
+
+/// 终端宽度缓存，0 表示未知
+static TERMINAL_WIDTH: AtomicUsize = AtomicUsize::new(0);
+
+/// 终端大小改变（SIGWINCH）后置位，下次使用时重新查询宽度
+static RESIZED: LazyLock<Arc<AtomicBool>> = LazyLock::new(|| {
+    let resized = Arc::new(AtomicBool::new(true));
+    #[cfg(unix)]
+    let _ = signal_hook::flag::register(signal_hook::consts::SIGWINCH, Arc::clone(&resized));
+    resized
+});
 
 pub struct LineFormatter {
     max_width: usize,
@@ -7,290 +24,208 @@ pub struct LineFormatter {
 }
 
 impl LineFormatter {
-    pub fn new() -> Self {
+    /// 按终端宽度截断，匹配内容前后保留 `context_chars` 个字符
+    pub fn with_context(context_chars: usize) -> Self {
         let max_width = Self::get_terminal_width().unwrap_or(80);
+        Self::with_width(max_width, context_chars)
+    }
+
+    pub fn with_width(max_width: usize, context_chars: usize) -> Self {
         LineFormatter {
             max_width,
-            context_chars: 20, // 匹配内容前后保留的字符数
+            context_chars,
         }
     }
 
     fn get_terminal_width() -> Option<usize> {
-        // 尝试从环境变量获取终端宽度
-        if let Ok(cols) = env::var("COLUMNS") {
-            if let Ok(width) = cols.parse::<usize>() {
-                return Some(width);
-            }
+        if RESIZED.swap(false, Ordering::Relaxed) {
+            TERMINAL_WIDTH.store(Self::query_terminal_width().unwrap_or(0), Ordering::Relaxed);
+        }
+        match TERMINAL_WIDTH.load(Ordering::Relaxed) {
+            0 => None,
+            width => Some(width),
         }
-        
-        // Windows 系统使用默认宽度
-        Some(80)
     }
 
+    fn query_terminal_width() -> Option<usize> {
+        // 优先查询标准输出所在终端的实际大小（ioctl）
+        if let Some((terminal_size::Width(width), _)) = terminal_size::terminal_size() {
+            return Some(width as usize);
+        }
+
+        // 输出不是终端时，尝试从环境变量获取终端宽度
+        env::var("COLUMNS").ok()?.parse::<usize>().ok()
+    }
+
+    /// Truncates `line_content` around the matches so it fits the terminal
+    /// width and highlights them. `match_spans` are char offsets.
     pub fn format_long_line(
         &self,
         line_content: &str,
-        query: &str,
-        match_indices: &[usize],
+        match_spans: &[(usize, usize)],
     ) -> String {
-        // 使用字符边界安全的处理
-        let line_chars: Vec<char> = line_content.chars().collect();
-        let line_len = line_chars.len();
-        
-        if line_len <= self.max_width {
-            return self.highlight_matches_safe(line_content, query, match_indices);
-        }
-
-        // 找到所有匹配的位置（字符索引）
-        let mut all_matches = Vec::new();
-        for &start_idx in match_indices {
-            let end_idx = start_idx + query.len();
-            all_matches.push((start_idx, end_idx));
-        }
-
-        if all_matches.is_empty() {
-            // 如果没有匹配，显示开头部分
-            let end_char = (self.max_width - 3).min(line_len);
-            let truncated: String = line_chars[..end_char].iter().collect();
-            return format!("{}...", truncated);
-        }
-
-        // 计算需要显示的区域（字符索引）
-        let first_match = all_matches[0].0;
-        let last_match = all_matches.last().unwrap().1;
-        
-        let start_pos = first_match.saturating_sub(self.context_chars);
-        let end_pos = (last_match + self.context_chars).min(line_len);
-        
-        // 确保总长度不超过最大宽度
-        let available_width = self.max_width - 6; // 为"..."和空格预留空间
-        let mut display_start = start_pos;
-        let mut display_end = end_pos;
-        
-        if end_pos > start_pos && end_pos - start_pos > available_width {
-            // 如果内容仍然太长，以第一个匹配为中心
-            let center = first_match;
-            let half_width = available_width / 2;
-            display_start = center.saturating_sub(half_width);
-            display_end = (center + half_width).min(line_len);
-        }
-        
-        // 确保 display_start <= display_end 且都在有效范围内
-        display_start = display_start.min(line_len);
-        display_end = display_end.max(display_start).min(line_len);
-
-        let mut result = String::new();
-        
-        // 添加前缀省略号
-        if display_start > 0 {
-            result.push_str("...");
-        }
-        
-        // 截取并高亮显示的内容
-        let segment_chars = &line_chars[display_start..display_end];
-        let segment: String = segment_chars.iter().collect();
-        
-        let adjusted_indices: Vec<usize> = match_indices
-            .iter()
-            .filter(|&&idx| idx >= display_start && idx < display_end)
-            .map(|&idx| idx - display_start)
-            .collect();
-        
-        let highlighted = self.highlight_matches_safe(&segment, query, &adjusted_indices);
-        result.push_str(&highlighted);
-        
-        // 添加后缀省略号
-        if display_end < line_len {
-            result.push_str("...");
-        }
-        
-        result
+        self.truncate_line(line_content, match_spans, |matched| paint(Role::Match, matched))
     }
 
     pub fn format_long_line_pure(
         &self,
         line_content: &str,
-        query: &str,
-        match_indices: &[usize],
+        match_spans: &[(usize, usize)],
+    ) -> String {
+        self.truncate_line(line_content, match_spans, str::to_string)
+    }
+
+    fn truncate_line(
+        &self,
+        line_content: &str,
+        match_spans: &[(usize, usize)],
+        paint_match: impl Fn(&str) -> String,
     ) -> String {
-        // 使用字符边界安全的处理
+        // 使用字符边界安全的处理，宽度按终端显示列计算（中日韩字符占两列）
         let line_chars: Vec<char> = line_content.chars().collect();
+        let char_widths: Vec<usize> = line_chars.iter().map(|c| c.width().unwrap_or(0)).collect();
         let line_len = line_chars.len();
-        
-        if line_len <= self.max_width {
-            return self.highlight_matches_safe_pure(line_content, query, match_indices);
-        }
 
-        // 找到所有匹配的位置（字符索引）
-        let mut all_matches = Vec::new();
-        for &start_idx in match_indices {
-            let end_idx = start_idx + query.len();
-            all_matches.push((start_idx, end_idx));
+        if char_widths.iter().sum::<usize>() <= self.max_width {
+            return Self::highlight_matches(line_content, match_spans, paint_match);
         }
 
-        if all_matches.is_empty() {
+        let width_between = |start: usize, end: usize| -> usize { char_widths[start..end].iter().sum() };
+
+        if match_spans.is_empty() {
             // 如果没有匹配，显示开头部分
-            let end_char = (self.max_width - 3).min(line_len);
+            let mut end_char = 0;
+            while end_char < line_len
+                && width_between(0, end_char + 1) <= self.max_width.saturating_sub(3)
+            {
+                end_char += 1;
+            }
             let truncated: String = line_chars[..end_char].iter().collect();
             return format!("{}...", truncated);
         }
 
         // 计算需要显示的区域（字符索引）
-        let first_match = all_matches[0].0;
-        let last_match = all_matches.last().unwrap().1;
-        
-        let start_pos = first_match.saturating_sub(self.context_chars);
-        let end_pos = (last_match + self.context_chars).min(line_len);
-        
-        // 确保总长度不超过最大宽度
-        let available_width = self.max_width - 6; // 为"..."和空格预留空间
-        let mut display_start = start_pos;
-        let mut display_end = end_pos;
-        
-        if end_pos > start_pos && end_pos - start_pos > available_width {
+        let first_match = match_spans[0].0.min(line_len);
+        let last_match = match_spans.last().unwrap().1.min(line_len);
+
+        // 匹配前后各保留 context_chars 列的上下文
+        let mut display_start = first_match;
+        while display_start > 0 && width_between(display_start - 1, first_match) <= self.context_chars {
+            display_start -= 1;
+        }
+        let mut display_end = last_match.max(display_start);
+        while display_end < line_len && width_between(last_match, display_end + 1) <= self.context_chars {
+            display_end += 1;
+        }
+
+        // 确保总宽度不超过最大宽度
+        let available_width = self.max_width.saturating_sub(6); // 为"..."和空格预留空间
+        if width_between(display_start, display_end) > available_width {
             // 如果内容仍然太长，以第一个匹配为中心
-            let center = first_match;
             let half_width = available_width / 2;
-            display_start = center.saturating_sub(half_width);
-            display_end = (center + half_width).min(line_len);
+            display_start = first_match;
+            while display_start > 0 && width_between(display_start - 1, first_match) <= half_width {
+                display_start -= 1;
+            }
+            display_end = first_match;
+            while display_end < line_len
+                && width_between(display_start, display_end + 1) <= available_width
+            {
+                display_end += 1;
+            }
         }
-        
-        // 确保 display_start <= display_end 且都在有效范围内
-        display_start = display_start.min(line_len);
-        display_end = display_end.max(display_start).min(line_len);
 
         let mut result = String::new();
-        
+
         // 添加前缀省略号
         if display_start > 0 {
             result.push_str("...");
         }
-        
+
         // 截取并高亮显示的内容
-        let segment_chars = &line_chars[display_start..display_end];
-        let segment: String = segment_chars.iter().collect();
-        
-        let adjusted_indices: Vec<usize> = match_indices
+        let segment: String = line_chars[display_start..display_end].iter().collect();
+
+        let adjusted_spans: Vec<(usize, usize)> = match_spans
             .iter()
-            .filter(|&&idx| idx >= display_start && idx < display_end)
-            .map(|&idx| idx - display_start)
+            .filter(|&&(start, _)| start >= display_start && start < display_end)
+            .map(|&(start, end)| (start - display_start, end.min(display_end) - display_start))
             .collect();
-        
-        let highlighted = self.highlight_matches_safe_pure(&segment, query, &adjusted_indices);
-        result.push_str(&highlighted);
-        
+
+        result.push_str(&Self::highlight_matches(&segment, &adjusted_spans, paint_match));
+
         // 添加后缀省略号
         if display_end < line_len {
             result.push_str("...");
         }
-        
+
         result
     }
 
-    fn highlight_matches_safe(
-        &self,
+    /// 用 `paint_match` 渲染 `content` 中的匹配，`match_spans` 是字符偏移
+    fn highlight_matches(
         content: &str,
-        query: &str,
-        match_indices: &[usize],
+        match_spans: &[(usize, usize)],
+        paint_match: impl Fn(&str) -> String,
     ) -> String {
-        if match_indices.is_empty() {
+        if match_spans.is_empty() {
             return content.to_string();
         }
 
+        // 使用字符边界安全的切片，只收集一次字符
+        let content_chars: Vec<char> = content.chars().collect();
         let mut result = String::new();
         let mut last_end = 0;
 
-        for &start_idx in match_indices {
-            let end_idx = start_idx + query.len();
-            
-            // 使用字符边界安全的切片
-            let content_chars: Vec<char> = content.chars().collect();
+        for &(start_idx, end_idx) in match_spans {
             if start_idx >= content_chars.len() {
                 continue;
             }
-            
             let actual_end = end_idx.min(content_chars.len());
-            
+
             // 添加匹配前的文本
             if start_idx > last_end {
-                let before: String = content_chars[last_end..start_idx].iter().collect();
-                result.push_str(&before);
+                result.extend(&content_chars[last_end..start_idx]);
             }
-            
+
             // 添加高亮的匹配文本
             let matched: String = content_chars[start_idx..actual_end].iter().collect();
-            result.push_str(&matched.on_red().to_string());
-            
+            result.push_str(&paint_match(&matched));
+
             last_end = actual_end;
         }
 
         // 添加剩余的文本
-        let content_chars: Vec<char> = content.chars().collect();
         if last_end < content_chars.len() {
-            let remaining: String = content_chars[last_end..].iter().collect();
-            result.push_str(&remaining);
+            result.extend(&content_chars[last_end..]);
         }
 
         result
     }
+}
 
-    fn highlight_matches_safe_pure(
-        &self,
-        content: &str,
-        query: &str,
-        match_indices: &[usize],
-    ) -> String {
-        if match_indices.is_empty() {
-            return content.to_string();
-        }
-
-        let mut result = String::new();
-        let mut last_end = 0;
-
-        for &start_idx in match_indices {
-            let end_idx = start_idx + query.len();
-            
-            // 使用字符边界安全的切片
-            let content_chars: Vec<char> = content.chars().collect();
-            if start_idx >= content_chars.len() {
-                continue;
-            }
-            
-            let actual_end = end_idx.min(content_chars.len());
-            
-            // 添加匹配前的文本
-            if start_idx > last_end {
-                let before: String = content_chars[last_end..start_idx].iter().collect();
-                result.push_str(&before);
-            }
-            
-            // 添加高亮的匹配文本
-            let matched: String = content_chars[start_idx..actual_end].iter().collect();
-            result.push_str(&matched.to_string());
-            
-            last_end = actual_end;
-        }
-
-        // 添加剩余的文本
-        let content_chars: Vec<char> = content.chars().collect();
-        if last_end < content_chars.len() {
-            let remaining: String = content_chars[last_end..].iter().collect();
-            result.push_str(&remaining);
-        }
-
-        result
+/// 按字节位置高亮匹配内容，不截断
+pub fn highlight_spans(text: &str, spans: &[(usize, usize)]) -> String {
+    let mut result = String::new();
+    let mut last_end = 0;
+    for &(start, end) in spans {
+        result.push_str(&text[last_end..start]);
+        result.push_str(&paint(Role::Match, &text[start..end]));
+        last_end = end;
     }
+    result.push_str(&text[last_end..]);
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use unicode_width::UnicodeWidthStr;
 
     #[test]
     fn test_short_line_formatting() {
-        let formatter = LineFormatter::new();
+        let formatter = LineFormatter::with_context(DEFAULT_CONTEXT_CHARS);
         let line = "This is a short line";
-        let result = formatter.format_long_line(line, "short", &[10]);
+        let result = formatter.format_long_line(line, &[(10, 15)]);
         assert!(result.contains("short"));
     }
 
@@ -302,10 +237,39 @@ mod tests {
         };
         
         let long_line = "This is a very long line with multiple words and the search term appears somewhere in the middle of this long content";
-        let result = formatter.format_long_line(long_line, "search", &[60]);
+        let result = formatter.format_long_line(long_line, &[(53, 59)]);
         
         assert!(result.contains("..."));
         assert!(result.contains("search"));
         assert!(result.len() <= 50);
     }
+
+    #[test]
+    fn test_wide_char_truncation() {
+        let formatter = LineFormatter {
+            max_width: 30,
+            context_chars: 6,
+        };
+
+        // 每个汉字占两列，按字符数截断会超出终端宽度
+        let line = "这是一个很长的中文行，其中包含搜索关键字以及后面更多的中文内容用于测试";
+        let start = line.chars().position(|c| c == '搜').unwrap();
+        let result = formatter.format_long_line_pure(line, &[(start, start + 2)]);
+
+        assert!(result.contains("搜索"));
+        assert!(result.starts_with("...") && result.ends_with("..."));
+        assert!(result.width() <= 30);
+    }
+
+    #[test]
+    fn test_highlight_after_wide_chars() {
+        let formatter = LineFormatter {
+            max_width: 80,
+            context_chars: 20,
+        };
+
+        // 匹配位置是字符索引，前面的中文和 emoji 不能导致高亮错位
+        let result = formatter.format_long_line("中文🎉 abc", &[(4, 7)]);
+        assert_eq!(result, format!("中文🎉 {}", paint(Role::Match, "abc")));
+    }
 }
@@ -0,0 +1,315 @@
+
+use chrono::{DateTime, Local};
+
+use crate::display::DisPlay;
+use crate::options::{OutputMode, SearchOptions};
+use crate::report::Report;
+
+/// Inline style sheet, so the report works offline and as a single file
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 80em; padding: 0 1em; color: #222; }
+h1 code, .summary code { background: #f3f3f3; padding: 0 .3em; border-radius: 3px; }
+table { border-collapse: collapse; }
+.summary th, .summary td, .files th, .files td { text-align: left; padding: .25em 1em .25em 0; border-bottom: 1px solid #eee; }
+.files td.number { text-align: right; }
+#filter { width: 100%; box-sizing: border-box; margin: 1.5em 0 1em; padding: .5em; font-size: 1em; }
+details.file { border: 1px solid #ddd; border-radius: 4px; margin-bottom: .75em; }
+details.file > summary { cursor: pointer; padding: .5em .75em; background: #fafafa; font-family: monospace; }
+details.file > summary .count { color: #777; font-family: system-ui, sans-serif; margin-left: 1em; }
+.matches { width: 100%; font-family: monospace; }
+.matches td { padding: .15em .75em; vertical-align: top; }
+.matches td.line, .matches td.column { text-align: right; color: #888; white-space: nowrap; width: 1%; }
+.matches td.line a { color: inherit; text-decoration: none; }
+.matches td.text { white-space: pre-wrap; word-break: break-all; }
+.matches tr:target { background: #fff6cc; }
+.matches tbody.gap { border-top: 1px dashed #ccc; }
+.matches tr.context td.text { color: #777; }
+mark { background: #ffd54f; }
+"#;
+
+/// Client-side filtering and opening of the section an anchor points to
+const SCRIPT: &str = r#"
+document.getElementById('filter').addEventListener('input', function () {
+  var needle = this.value.toLowerCase();
+  document.querySelectorAll('details.file').forEach(function (file) {
+    var pathMatches = file.dataset.path.toLowerCase().indexOf(needle) !== -1;
+    var visible = 0;
+    file.querySelectorAll('tbody.hit').forEach(function (hit) {
+      var row = hit.querySelector('tr.match');
+      var show = pathMatches || row.textContent.toLowerCase().indexOf(needle) !== -1;
+      hit.hidden = !show;
+      if (show) visible++;
+    });
+    file.hidden = !pathMatches && visible === 0;
+  });
+});
+function revealAnchor() {
+  var target = location.hash && document.getElementById(decodeURIComponent(location.hash.slice(1)));
+  if (!target) return;
+  var file = target.closest('details');
+  if (file) file.open = true;
+  target.scrollIntoView();
+}
+window.addEventListener('hashchange', revealAnchor);
+revealAnchor();
+"#;
+
+/// Renders a standalone HTML report: a summary of the run, a table of the
+/// reported files and one collapsible section per file with its lines and
+/// their context (`--html-context`). Every line has an anchor (`<file>-L<line>`) that stays the same between
+/// runs as long as the path and line do not change.
+pub fn render(report: &Report, generated_at: DateTime<Local>) -> String {
+    let anchors = file_anchors(report);
+    let total_matches: usize = report
+        .files()
+        .iter()
+        .flat_map(|(_, displays)| displays.iter())
+        .map(|display| display.match_count())
+        .sum();
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str(&format!("<title>rspfind: {}</title>\n", escape(report.query())));
+    html.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+    html.push_str(&format!(
+        "<h1>Search results for <code>{}</code></h1>\n",
+        escape(report.query())
+    ));
+
+    html.push_str("<table class=\"summary\">\n");
+    let summary = [
+        ("Query", format!("<code>{}</code>", escape(report.query()))),
+        ("Options", escape(&describe_options(report.options()))),
+        ("Generated", generated_at.format("%Y-%m-%d %H:%M:%S %:z").to_string()),
+        ("Files", report.files().len().to_string()),
+        ("Matching lines", report.total_lines().to_string()),
+        ("Matches", total_matches.to_string()),
+    ];
+    for (name, value) in summary {
+        html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", name, value));
+    }
+    html.push_str("</table>\n");
+
+    if !report.files().is_empty() {
+        html.push_str("<h2>Files</h2>\n<table class=\"files\">\n");
+        html.push_str("<tr><th>File</th><th>Lines</th><th>Matches</th></tr>\n");
+        for (&(path, displays), anchor) in report.files().iter().zip(&anchors) {
+            html.push_str(&format!(
+                "<tr><td><a href=\"#{}\">{}</a></td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>\n",
+                anchor,
+                escape(path),
+                displays.len(),
+                displays.iter().map(|display| display.match_count()).sum::<usize>()
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<input id=\"filter\" type=\"search\" placeholder=\"Filter by path or line content\">\n");
+    for (&(path, displays), anchor) in report.files().iter().zip(&anchors) {
+        html.push_str(&file_section(path, displays, anchor));
+    }
+
+    html.push_str(&format!("<script>{}</script>\n</body>\n</html>\n", SCRIPT));
+    html
+}
+
+fn file_section(path: &str, displays: &[DisPlay], anchor: &str) -> String {
+    let mut section = format!(
+        "<details class=\"file\" id=\"{}\" data-path=\"{}\" open>\n<summary>{}<span class=\"count\">{} lines</span></summary>\n",
+        anchor,
+        escape(path),
+        escape(path),
+        displays.len()
+    );
+    if !displays.is_empty() {
+        section.push_str("<table class=\"matches\">\n");
+        // Each line with its context is one group; a dashed border marks
+        // skipped lines between groups
+        let mut next_line = None;
+        for display in displays {
+            let first_line = display.line_number() - display.context_before().len();
+            let class = if next_line.is_some_and(|next| next != first_line) { "hit gap" } else { "hit" };
+            section.push_str(&format!("<tbody class=\"{}\">\n", class));
+            for (offset, line) in display.context_before().iter().enumerate() {
+                section.push_str(&context_row(first_line + offset, line));
+            }
+
+            let line_anchor = format!("{}-L{}", anchor, display.line_number());
+            let column = display.columns().first().map(|&(start, _)| start).unwrap_or_default();
+            section.push_str(&format!(
+                "<tr class=\"match\" id=\"{id}\"><td class=\"line\"><a href=\"#{id}\">{line}</a></td><td class=\"column\">{column}</td><td class=\"text\">{text}</td></tr>\n",
+                id = line_anchor,
+                line = display.line_number(),
+                column = column,
+                text = highlight(display.line_content(), display.match_spans())
+            ));
+
+            for (offset, line) in display.context_after().iter().enumerate() {
+                section.push_str(&context_row(display.line_number() + 1 + offset, line));
+            }
+            section.push_str("</tbody>\n");
+            next_line = Some(display.line_number() + display.context_after().len() + 1);
+        }
+        section.push_str("</table>\n");
+    }
+    section.push_str("</details>\n");
+    section
+}
+
+fn context_row(line_number: usize, line: &str) -> String {
+    format!(
+        "<tr class=\"context\"><td class=\"line\">{}</td><td class=\"column\"></td><td class=\"text\">{}</td></tr>\n",
+        line_number,
+        escape(line)
+    )
+}
+
+/// Anchors for the reported files, derived from their full paths
+fn file_anchors(report: &Report) -> Vec<String> {
+    report.files().iter().map(|(path, _)| anchor(path)).collect()
+}
+
+/// `f-` followed by the path, with every byte other than ASCII letters,
+/// digits, `.`, `_` and `/` written as `~XX`. Distinct paths always get
+/// distinct anchors, and the file anchor never contains the `-L` that
+/// starts a line anchor. `~` is left alone by URL decoding, so the script
+/// finds the element the fragment names.
+fn anchor(path: &str) -> String {
+    let mut anchor = String::from("f-");
+    for &byte in path.as_bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'_' | b'/') {
+            anchor.push(byte as char);
+        } else {
+            anchor.push_str(&format!("~{:02X}", byte));
+        }
+    }
+    anchor
+}
+
+/// Escapes the line and wraps the matched byte spans in `<mark>`
+fn highlight(line: &str, spans: &[(usize, usize)]) -> String {
+    let mut html = String::new();
+    let mut last_end = 0;
+    for &(start, end) in spans {
+        html.push_str(&escape(&line[last_end..start]));
+        html.push_str(&format!("<mark>{}</mark>", escape(&line[start..end])));
+        last_end = end;
+    }
+    html.push_str(&escape(&line[last_end..]));
+    html
+}
+
+fn describe_options(options: &SearchOptions) -> String {
+    let mut flags: Vec<String> = Vec::new();
+    if options.ignore_case {
+        flags.push("ignore case".to_string());
+    }
+    if options.smart_case {
+        flags.push("smart case".to_string());
+    }
+    if options.word_regexp {
+        flags.push("whole words".to_string());
+    }
+    if options.line_regexp {
+        flags.push("whole lines".to_string());
+    }
+    if options.invert_match {
+        flags.push("inverted".to_string());
+    }
+    match options.mode {
+        OutputMode::FilesWithMatches => flags.push("files with matches".to_string()),
+        OutputMode::FilesWithoutMatch => flags.push("files without match".to_string()),
+        _ => {}
+    }
+    if let Some(max_count) = options.max_count {
+        flags.push(format!("at most {} lines per file", max_count));
+    }
+    if let Some(max_total) = options.max_total {
+        flags.push(format!("at most {} lines in total", max_total));
+    }
+    if options.search_zip {
+        flags.push("compressed files".to_string());
+    }
+    if options.search_archives {
+        flags.push("archives".to_string());
+    }
+    if flags.is_empty() {
+        "none".to_string()
+    } else {
+        flags.join(", ")
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::ColumnUnit;
+    use crate::report::sample_display;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_html_report() {
+        let mut display = sample_display(ColumnUnit::Char);
+        display.set_context_before(vec!["fn main() {".to_string()]);
+        display.push_context_after("}".to_string());
+        let later = DisPlay::new(
+            9,
+            vec![(10, 12)],
+            "let x = \"<ab>\" & 'ab';".to_string(),
+            ColumnUnit::Char,
+        );
+        let display_map = HashMap::from([
+            ("src/a.rs".to_string(), vec![display, later]),
+            ("src-a.rs".to_string(), vec![]),
+        ]);
+        let options = SearchOptions::default();
+        let report = Report::new("ab", &display_map, &options);
+
+        let generated_at = Local.with_ymd_and_hms(2025, 3, 9, 14, 5, 0).unwrap();
+        let html = render(&report, generated_at);
+        assert!(html.contains("字 <mark>ab</mark>, <mark>ab</mark>"));
+        assert!(html.contains("&quot;&lt;<mark>ab</mark>&gt;&quot; &amp; &#39;ab&#39;"));
+        assert!(html.contains("id=\"f-src/a.rs-L3\""));
+        assert!(html.contains("<tr class=\"context\"><td class=\"line\">2</td><td class=\"column\"></td><td class=\"text\">fn main() {</td></tr>"));
+        assert!(html.contains("<td class=\"line\">4</td><td class=\"column\"></td><td class=\"text\">}</td>"));
+        // Line 10 does not follow the context of line 3
+        assert!(html.contains("<tbody class=\"hit gap\">\n<tr class=\"match\" id=\"f-src/a.rs-L10\">"));
+        assert!(html.contains("2025-03-09 14:05:00"));
+        assert!(!html.contains("<ab>"));
+    }
+
+    #[test]
+    fn test_colliding_anchors() {
+        let mut display_map = HashMap::new();
+        display_map.insert("a/b".to_string(), Vec::new());
+        display_map.insert("a-b".to_string(), Vec::new());
+        display_map.insert("文档/说明.md".to_string(), Vec::new());
+        let options = SearchOptions {
+            mode: OutputMode::FilesWithoutMatch,
+            ..Default::default()
+        };
+        let report = Report::new("x", &display_map, &options);
+        assert_eq!(
+            file_anchors(&report),
+            vec!["f-a~2Db", "f-a/b", "f-~E6~96~87~E6~A1~A3/~E8~AF~B4~E6~98~8E.md"]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+
+/// Languages of the user interface
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+    /// Simplified Chinese
+    Zh,
+}
+
+impl Lang {
+    /// The language of the locale in `LC_ALL`, `LC_MESSAGES` or `LANG`
+    pub fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        if locale.to_ascii_lowercase().starts_with("zh") {
+            Lang::Zh
+        } else {
+            Lang::En
+        }
+    }
+
+    fn catalog(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Lang::En => EN,
+            Lang::Zh => ZH,
+        }
+    }
+}
+
+/// Messages are looked up by key; `{name}` placeholders are filled in by
+/// `tr_with`. Every key must exist in every catalog.
+const EN: &[(&str, &str)] = &[
+    ("error", "Error: {message}"),
+    ("warning", "Warning: {message}"),
+    ("path_missing", "'{path}' does not exist"),
+    ("path_invalid", "'{path}' is not a valid file or directory"),
+    ("path_canonicalize", "Cannot canonicalize path '{path}': {error}"),
+    ("cannot_read", "Cannot read '{path}': {error}"),
+    ("cannot_read_path_list", "Cannot read path list '{path}': {error}"),
+    ("symlink_loop", "Skipping '{path}': symlink loop back to '{ancestor}'"),
+    ("member_too_large", "Skipping '{path}': larger than --max-member-size ({limit})"),
+    ("cannot_run", "Cannot run '{program}': {error}"),
+    ("command_failed_for", "Command for '{path}' exited with {status}"),
+    ("command_failed", "Command exited with {status}"),
+    ("exec_requires_command", "--exec requires a command"),
+    ("exec_not_a_file", "Not running the command for '{path}': it is not a file on disk"),
+    ("no_valid_paths", "No valid files or directories found"),
+    ("no_search_paths", "Must specify at least one file or directory to search"),
+    ("stdin_used_twice", "Standard input cannot be both the path list (--files-from -) and a searched path (-)"),
+    ("diff_files_missing", "Both files must exist"),
+    (
+        "append_unsupported",
+        "--append cannot add a {format} report to a file; use text, csv, tsv or markdown",
+    ),
+    ("missing_subcommand", "Please specify a subcommand: find, files or diff"),
+    ("searching_in", "Searching in {paths}"),
+    ("comparing_files", "Comparing files {file1} and {file2}"),
+    ("output_saved", "Output saved to: {path}"),
+    ("matches_in_file", "Found the following matches in file '{file}': "),
+    ("match_summary", "Found {lines} matching lines in {files} files"),
+    ("line_details", "line number: {line} position: [{positions}] line content: {content}"),
+    ("omitted_long_line", "[Omitted long line with {count} matches]"),
+    (
+        "progress_template",
+        "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} files ({eta})",
+    ),
+    ("stats_files_searched", "Files searched:"),
+    ("stats_searched_with_members", "{files} (and {members} archive members)"),
+    ("stats_bytes_read", "Bytes read:"),
+    ("stats_files_skipped", "Files skipped:"),
+    (
+        "stats_skipped_reasons",
+        "{total} ({filtered} filtered out, {binary} binary, {unreadable} unreadable, {not_searched} not searched (stopped early))",
+    ),
+    ("stats_files_matched", "Files matched:"),
+    ("stats_matching_lines", "Matching lines:"),
+    ("stats_matches", "Matches:"),
+    ("stats_elapsed", "Elapsed:"),
+    ("stats_throughput", "Throughput:"),
+];
+
+const ZH: &[(&str, &str)] = &[
+    ("error", "错误：{message}"),
+    ("warning", "警告：{message}"),
+    ("path_missing", "'{path}' 不存在"),
+    ("path_invalid", "'{path}' 不是有效的文件或目录"),
+    ("path_canonicalize", "无法规范化路径 '{path}'：{error}"),
+    ("cannot_read", "无法读取 '{path}'：{error}"),
+    ("cannot_read_path_list", "无法读取路径列表 '{path}'：{error}"),
+    ("symlink_loop", "跳过 '{path}'：符号链接循环指向 '{ancestor}'"),
+    ("member_too_large", "跳过 '{path}'：超过 --max-member-size（{limit}）"),
+    ("cannot_run", "无法运行 '{program}'：{error}"),
+    ("command_failed_for", "'{path}' 的命令退出状态为 {status}"),
+    ("command_failed", "命令退出状态为 {status}"),
+    ("exec_requires_command", "--exec 需要指定命令"),
+    ("exec_not_a_file", "不对 '{path}' 执行命令：它不是磁盘上的文件"),
+    ("no_valid_paths", "未找到有效的文件或目录"),
+    ("no_search_paths", "必须至少指定一个要搜索的文件或目录"),
+    ("stdin_used_twice", "标准输入不能同时作为路径列表（--files-from -）和搜索路径（-）"),
+    ("diff_files_missing", "两个文件都必须存在"),
+    ("append_unsupported", "--append 不能向文件追加 {format} 报告；请使用 text、csv、tsv 或 markdown"),
+    ("missing_subcommand", "请指定子命令：find、files 或 diff"),
+    ("searching_in", "正在搜索 {paths}"),
+    ("comparing_files", "正在比较文件 {file1} 和 {file2}"),
+    ("output_saved", "输出已保存到：{path}"),
+    ("matches_in_file", "在文件 '{file}' 中找到以下匹配: "),
+    ("match_summary", "在 {files} 个文件中找到 {lines} 个匹配行"),
+    ("line_details", "行号: {line} 位置: [{positions}] 行内容: {content}"),
+    ("omitted_long_line", "[已省略包含 {count} 处匹配的长行]"),
+    (
+        "progress_template",
+        "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} 个文件 ({eta})",
+    ),
+    ("stats_files_searched", "已搜索文件："),
+    ("stats_searched_with_members", "{files}（另有 {members} 个归档成员）"),
+    ("stats_bytes_read", "读取字节："),
+    ("stats_files_skipped", "跳过文件："),
+    (
+        "stats_skipped_reasons",
+        "{total}（过滤排除 {filtered}，二进制 {binary}，无法读取 {unreadable}，提前结束未搜索 {not_searched}）",
+    ),
+    ("stats_files_matched", "匹配文件："),
+    ("stats_matching_lines", "匹配行数："),
+    ("stats_matches", "匹配次数："),
+    ("stats_elapsed", "耗时："),
+    ("stats_throughput", "吞吐量："),
+];
+
+/// The language of this run
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Sets the language of this run. Until it is called (e.g. in tests)
+/// messages are in English.
+pub fn init(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+fn lookup(catalog: &[(&str, &'static str)], key: &str) -> Option<&'static str> {
+    catalog.iter().find(|(name, _)| *name == key).map(|(_, text)| *text)
+}
+
+/// The message `key` in the language of this run, falling back to English
+pub fn tr(key: &'static str) -> &'static str {
+    let lang = LANG.get().copied().unwrap_or_default();
+    lookup(lang.catalog(), key)
+        .or_else(|| lookup(EN, key))
+        .unwrap_or(key)
+}
+
+/// The message `key` with its `{name}` placeholders replaced by `args`.
+/// Placeholders without an argument are kept as they are.
+pub fn tr_with(key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    let mut message = String::new();
+    let mut rest = tr(key);
+    while let Some(start) = rest.find('{') {
+        message.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let value = placeholder.find('}').and_then(|end| {
+            let name = &placeholder[1..end];
+            args.iter().find(|(arg, _)| *arg == name).map(|(_, value)| (value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                message.push_str(&value.to_string());
+                rest = &placeholder[end + 1..];
+            }
+            None => {
+                message.push('{');
+                rest = &placeholder[1..];
+            }
+        }
+    }
+    message.push_str(rest);
+    message
+}
+
+/// Prints a localized warning to stderr
+pub fn warn(message: &str) {
+    eprintln!("{}", tr_with("warning", &[("message", &message)]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        text.split('{').skip(1).filter_map(|rest| rest.split_once('}')).map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn test_catalogs_are_complete() {
+        let langs = [Lang::En, Lang::Zh];
+        for lang in langs {
+            for other in langs {
+                for (key, text) in lang.catalog() {
+                    let translated = lookup(other.catalog(), key)
+                        .unwrap_or_else(|| panic!("key '{}' is missing in {:?}", key, other));
+                    assert_eq!(placeholders(text), placeholders(translated), "placeholders of '{}'", key);
+                }
+            }
+            let template = lookup(lang.catalog(), "progress_template").unwrap();
+            assert!(indicatif::ProgressStyle::with_template(template).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_tr_with() {
+        assert_eq!(
+            tr_with("match_summary", &[("lines", &3), ("files", &2)]),
+            "Found 3 matching lines in 2 files"
+        );
+        assert_eq!(
+            tr_with("comparing_files", &[("file1", &"{file2}"), ("file2", &"b")]),
+            "Comparing files {file2} and b"
+        );
+    }
+}
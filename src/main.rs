@@ -1,22 +1,54 @@
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
-use std::process::Output;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Instant, SystemTime};
 
 use anyhow::{Result, anyhow};
-use clap::{Parser, Subcommand};
+use chrono::Local;
+use clap::{Args, Parser, Subcommand};
+use globset::{Glob, GlobSetBuilder};
 
 mod file;
-use file::File;
+mod filter;
+use filter::FileFilter;
+mod walk;
+use walk::WalkOptions;
+mod exec;
+use exec::ExecTarget;
+mod names;
+use names::NameMatcher;
 
+mod archive;
+mod color;
+use color::{ColorChoice, ColorSpec, Palette, Role, Theme, paint};
+use archive::{ArchiveOptions, DEFAULT_MAX_ARCHIVE_DEPTH, MEMBER_SEPARATOR};
+mod decompress;
 mod display;
 use display::DisPlay;
 
 mod format;
+use format::DEFAULT_CONTEXT_CHARS;
+mod html;
+mod i18n;
+use i18n::{Lang, tr, tr_with, warn};
+mod matcher;
+mod options;
+use options::{ColumnUnit, LineLayout, OutputMode, SearchOptions, WrapOptions};
+mod output;
+mod pager;
+use pager::Paging;
 mod parallel;
-use parallel::ParallelProcessor;
-
-use owo_colors::OwoColorize;
+mod report;
+mod sarif;
+mod stats;
+use stats::ResultCounts;
+use sarif::{SarifLevel, SarifOptions};
+mod template;
+use template::{OutputTemplates, Template};
+use report::{CsvColumn, CsvOptions, OutputFormat, Report};
+use parallel::{ParallelProcessor, STDIN_LABEL, STDIN_PATH};
 
 use similar::{ChangeTag, TextDiff};
 
@@ -27,26 +59,79 @@ use similar::{ChangeTag, TextDiff};
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    #[command(flatten)]
+    color: ColorArgs,
+
+    #[command(flatten)]
+    pager: PagerArgs,
+
+    /// Language of messages (default: from LC_ALL, LC_MESSAGES or LANG)
+    #[arg(long, global = true, value_enum)]
+    lang: Option<Lang>,
 }
 
-#[derive(Subcommand)]
-enum Commands {
-    Find {
-        #[arg(short, long, required = true)]
-        query: String,
+// Pager flags, accepted before or after the subcommand
+#[derive(Args)]
+struct PagerArgs {
+    /// Show the output through the pager ($RSPFIND_PAGER, $PAGER or `less -RX`)
+    /// whenever stdout is a terminal, even when it fits on one screen
+    #[arg(long, global = true, overrides_with = "no_pager")]
+    pager: bool,
+
+    /// Never page the output
+    #[arg(long, global = true)]
+    no_pager: bool,
+}
+
+impl PagerArgs {
+    fn paging(&self) -> Paging {
+        if self.no_pager {
+            Paging::Never
+        } else if self.pager {
+            Paging::Always
+        } else {
+            Paging::Auto
+        }
+    }
+}
 
-        #[arg(short, long, num_args=1..)]
-        file_path: Vec<String>,
+// Terminal color flags, accepted before or after the subcommand
+#[derive(Args)]
+struct ColorArgs {
+    /// When to color the output
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
 
-        #[arg(short, long, num_args=1..)]
-        dir: Vec<String>,
+    /// Built-in palette
+    #[arg(long, global = true, value_enum, env = "RSPFIND_THEME", default_value_t = Theme::Dark)]
+    theme: Theme,
 
-        #[arg(short, long, default_value = "false")]
-        ignore_case: bool,
+    /// Change the palette, e.g. `match:fg:black,match:bg:yellow,path:style:bold`
+    #[arg(long, global = true, value_name = "SPEC", env = "RSPFIND_COLORS", value_delimiter = ',')]
+    colors: Vec<ColorSpec>,
+}
 
-        #[arg(short, long)]
-        output: Option<String>,
-    },
+impl ColorArgs {
+    /// The palette of this run, or `None` when colors are off
+    fn palette(&self) -> Option<Palette> {
+        if !self.color.enabled() {
+            return None;
+        }
+        let mut palette = Palette::new(self.theme);
+        for spec in &self.colors {
+            palette.apply(spec);
+        }
+        Some(palette)
+    }
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Find lines containing the query in files and directories
+    Find(Box<FindArgs>),
+    /// Find files whose name or path matches the query
+    Files(Box<FilesArgs>),
     Diff {
         #[arg(long)]
         file1: String,
@@ -56,42 +141,712 @@ enum Commands {
     },
 }
 
-fn handle_file_path_vec(
+/// Where and how `find` writes its report besides the terminal output
+struct ReportOptions {
+    /// Report file given with --output
+    file: Option<PathBuf>,
+    /// Report format; without a file the report replaces the terminal output
+    format: Option<OutputFormat>,
+    append: bool,
+    csv: CsvOptions,
+    sarif: SarifOptions,
+}
+
+// Command execution flags shared by `find` and `files`
+#[derive(Args)]
+struct ExecArgs {
+    /// Run CMD for each resulting file instead of printing it. `{}` is replaced
+    /// by the path (appended when absent), `{line}` and `{col}` by the location
+    /// of the first match. Terminate the command with `;`.
+    #[arg(long, num_args = 1.., allow_hyphen_values = true, value_terminator = ";", value_name = "CMD", group = "exec_mode")]
+    exec: Vec<String>,
+
+    /// Run CMD once with all resulting files; `{}` expands to every path
+    #[arg(long, num_args = 1.., allow_hyphen_values = true, value_terminator = ";", value_name = "CMD", group = "exec_mode")]
+    exec_batch: Vec<String>,
+
+    /// Number of --exec commands to run in parallel
+    #[arg(short, long, value_name = "NUM", default_value_t = 1)]
+    jobs: usize,
+}
+
+impl ExecArgs {
+    fn is_enabled(&self) -> bool {
+        !self.exec.is_empty() || !self.exec_batch.is_empty()
+    }
+
+    /// Runs the configured command(s) and returns the exit code to report.
+    /// A failing command gives EXIT_ERROR, since its own status could be
+    /// mistaken for "no match".
+    fn run(&self, targets: Vec<ExecTarget>) -> Result<i32> {
+        let succeeded = if self.exec_batch.is_empty() {
+            exec::exec_each(&self.exec, &targets, self.jobs)?
+        } else {
+            let paths: Vec<String> = targets.into_iter().map(|target| target.path).collect();
+            exec::exec_batch(&self.exec_batch, &paths)?
+        };
+        Ok(if succeeded { EXIT_MATCH } else { EXIT_ERROR })
+    }
+}
+
+// Directory traversal and file filter flags shared by `find` and `files`.
+// Like the other flattened flag structs this has no doc comment, which clap
+// would show as the about text of both commands.
+#[derive(Args)]
+struct WalkArgs {
+    /// Descend at most NUM directory levels (1 = only the directory's own files)
+    #[arg(long, value_name = "NUM")]
+    max_depth: Option<usize>,
+
+    /// Skip files less than NUM directory levels deep
+    #[arg(long, value_name = "NUM")]
+    min_depth: Option<usize>,
+
+    /// Follow symbolic links while walking directories
+    #[arg(long)]
+    follow: bool,
+
+    /// Do not cross filesystem boundaries (mount points)
+    #[arg(long)]
+    one_file_system: bool,
+
+    /// Skip files larger than SIZE (K, M and G suffixes allowed)
+    #[arg(long, value_name = "SIZE", value_parser = filter::parse_size)]
+    max_filesize: Option<u64>,
+
+    /// Skip files smaller than SIZE (K, M and G suffixes allowed)
+    #[arg(long, value_name = "SIZE", value_parser = filter::parse_size)]
+    min_filesize: Option<u64>,
+
+    /// Only search files modified within DURATION (e.g. 2h, 7d) or after FILE
+    #[arg(long, value_name = "DURATION|FILE", value_parser = filter::parse_time_bound)]
+    newer_than: Option<SystemTime>,
+
+    /// Only search files modified more than DURATION ago or before FILE
+    #[arg(long, value_name = "DURATION|FILE", value_parser = filter::parse_time_bound)]
+    older_than: Option<SystemTime>,
+
+    /// Only search files owned by this user id
+    #[cfg(unix)]
+    #[arg(long, value_name = "UID")]
+    owner: Option<u32>,
+
+    /// Only search files having all of these octal permission bits set (e.g. 644)
+    #[cfg(unix)]
+    #[arg(long, value_name = "MODE", value_parser = filter::parse_permissions)]
+    perm: Option<u32>,
+}
+
+impl WalkArgs {
+    fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            max_depth: self.max_depth,
+            min_depth: self.min_depth,
+            follow_links: self.follow,
+            one_file_system: self.one_file_system,
+            filter: self.file_filter(),
+        }
+    }
+
+    fn file_filter(&self) -> FileFilter {
+        #[cfg_attr(not(unix), allow(unused_mut))]
+        let mut filter = FileFilter {
+            min_size: self.min_filesize,
+            max_size: self.max_filesize,
+            newer_than: self.newer_than,
+            older_than: self.older_than,
+            ..Default::default()
+        };
+        #[cfg(unix)]
+        {
+            filter.owner = self.owner;
+            filter.permissions = self.perm;
+        }
+        filter
+    }
+}
+
+#[derive(Args)]
+struct FindArgs {
+    #[arg(short, long, required = true)]
     query: String,
-    file_paths: Vec<String>,
+
+    /// Files and directories to search
+    #[arg(value_name = "PATH")]
+    paths: Vec<String>,
+
+    #[arg(short, long, num_args=1..)]
+    file_path: Vec<String>,
+
+    #[arg(short, long, num_args=1..)]
+    dir: Vec<String>,
+
+    /// Read the paths to search from FILE, one per line or NUL-separated (`-` for stdin)
+    #[arg(long, value_name = "FILE")]
+    files_from: Option<String>,
+
+    #[arg(short, long, default_value = "false")]
     ignore_case: bool,
-    out_dir: Option<String>,
-) -> Result<()> {
-    let valid_file_paths = find_valid_paths(file_paths)?;
-    let mut display_map: HashMap<String, Vec<DisPlay>> = HashMap::new();
-    for file_path in valid_file_paths {
-        let content = fs::read_to_string(&file_path);
-        let content = match content {
-            Ok(c) => c,
-            Err(_) => continue,
+
+    /// Ignore case only when the query is all lowercase
+    #[arg(short = 'S', long)]
+    smart_case: bool,
+
+    /// Also write the results to FILE; a directory receives `output.<ext>`.
+    /// `{date}`, `{time}` and `{query}` are expanded in the path.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// Format of the report written with --output (by default guessed from the
+    /// file extension); without --output the report is printed instead
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    output_format: Option<OutputFormat>,
+
+    /// Append to the --output file instead of replacing it (text, csv, tsv and
+    /// markdown reports only)
+    #[arg(long, requires = "output")]
+    append: bool,
+
+    /// Field delimiter of CSV and TSV reports (default `,` and tab)
+    #[arg(long, value_name = "CHAR")]
+    csv_delimiter: Option<char>,
+
+    /// Columns of CSV and TSV reports, in order (default: all)
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "COLUMNS")]
+    csv_columns: Vec<CsvColumn>,
+
+    /// Severity of SARIF results
+    #[arg(long, value_enum, value_name = "LEVEL", default_value_t = SarifLevel::Warning)]
+    sarif_level: SarifLevel,
+
+    /// Rule id of SARIF results (default: derived from the query)
+    #[arg(long, value_name = "ID")]
+    sarif_rule_id: Option<String>,
+
+    /// Lines of context shown before and after every match in HTML reports
+    #[arg(long, value_name = "NUM", default_value_t = 2)]
+    html_context: usize,
+
+    /// Only print the number of matching lines for each file
+    #[arg(short, long, group = "report")]
+    count: bool,
+
+    /// Only print the number of match occurrences for each file
+    #[arg(long, group = "report")]
+    count_matches: bool,
+
+    /// Only print the paths of files containing at least one match
+    #[arg(short = 'l', long, group = "report")]
+    files_with_matches: bool,
+
+    /// Only print the paths of files containing no match
+    #[arg(short = 'L', long, group = "report")]
+    files_without_match: bool,
+
+    /// Print nothing and stop at the first match; only the exit status is reported
+    #[arg(long, group = "report", conflicts_with_all = ["output", "exec_mode"])]
+    quiet: bool,
+
+    /// Print `path:line:column:text` for every match, for editor quickfix lists
+    #[arg(long, group = "layout")]
+    vimgrep: bool,
+
+    /// Print the path once above the `line:text` lines of each file
+    #[arg(long, group = "layout")]
+    heading: bool,
+
+    /// Print `path:line:text` on every line
+    #[arg(long, group = "layout")]
+    no_heading: bool,
+
+    /// Print every match with a template, e.g. `{path}:{line}:{col}: {match:bold,red}`
+    #[arg(long, group = "layout", value_name = "TEMPLATE")]
+    format: Option<Template>,
+
+    /// Template printed above the matches of each file, e.g. `== {path} ({matches})`
+    #[arg(long, requires = "format", value_name = "TEMPLATE")]
+    file_header: Option<Template>,
+
+    /// Template printed below the matches of each file
+    #[arg(long, requires = "format", value_name = "TEMPLATE")]
+    file_footer: Option<Template>,
+
+    /// Template printed once before all files, e.g. `Results for {pattern}`
+    #[arg(long, requires = "format", value_name = "TEMPLATE")]
+    header: Option<Template>,
+
+    /// Template printed once after all files, e.g. `{matches} matches in {files} files`
+    #[arg(long, requires = "format", value_name = "TEMPLATE")]
+    footer: Option<Template>,
+
+    /// Terminate printed paths with a NUL byte instead of a newline (for `xargs -0`)
+    #[arg(short = '0', long)]
+    null: bool,
+
+    /// Report the lines that do NOT contain the query
+    #[arg(short = 'v', long)]
+    invert_match: bool,
+
+    /// Stop searching a file after NUM reported lines
+    #[arg(short, long, value_name = "NUM")]
+    max_count: Option<usize>,
+
+    /// Stop the whole search after NUM reported lines
+    #[arg(long, value_name = "NUM")]
+    max_total: Option<usize>,
+
+    /// Only match the query as a whole word
+    #[arg(short, long)]
+    word_regexp: bool,
+
+    /// Only match lines consisting entirely of the query
+    #[arg(short = 'x', long)]
+    line_regexp: bool,
+
+    /// Unit used to report match positions [default: char, byte with --vimgrep]
+    #[arg(long, value_enum, value_name = "UNIT")]
+    column_unit: Option<ColumnUnit>,
+
+    /// Omit lines wider than NUM terminal columns
+    #[arg(short = 'M', long, value_name = "NUM")]
+    max_columns: Option<usize>,
+
+    /// Show the matches of lines wider than --max-columns instead of omitting them
+    #[arg(long, requires = "max_columns")]
+    max_columns_preview: bool,
+
+    /// Print whole lines instead of fitting them to the terminal width
+    #[arg(long)]
+    no_truncate: bool,
+
+    /// Columns of context kept around the matches when a line is shortened
+    #[arg(long, value_name = "NUM", default_value_t = DEFAULT_CONTEXT_CHARS)]
+    context_chars: usize,
+
+    #[command(flatten)]
+    exec: ExecArgs,
+
+    #[command(flatten)]
+    walk: WalkArgs,
+
+    /// Search inside gzip, bzip2, xz and zstd compressed files
+    #[arg(short = 'z', long)]
+    search_zip: bool,
+
+    /// Search inside zip, jar, tar and compressed tar archives
+    #[arg(long)]
+    search_archives: bool,
+
+    /// Maximum number of nested archive levels to open
+    #[arg(long, value_name = "NUM", default_value_t = DEFAULT_MAX_ARCHIVE_DEPTH)]
+    max_archive_depth: usize,
+
+    /// Only search archive members whose path matches GLOB (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    archive_glob: Vec<Glob>,
+
+    /// Skip archive members larger than SIZE instead of reading them into memory
+    #[arg(long, value_name = "SIZE", default_value = "64M", value_parser = filter::parse_size)]
+    max_member_size: u64,
+
+    /// Stop with exit status 2 when a file cannot be read instead of skipping it
+    #[arg(long)]
+    fail_on_read_error: bool,
+
+    /// Do not draw the progress bar (it is also hidden when stderr is not a terminal)
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Print search statistics to stderr: files, bytes, skipped files, matches and timing
+    #[arg(long)]
+    stats: bool,
+}
+
+impl FindArgs {
+    fn search_options(&self) -> Result<SearchOptions> {
+        let mode = if self.count {
+            OutputMode::Count
+        } else if self.count_matches {
+            OutputMode::CountMatches
+        } else if self.files_with_matches {
+            OutputMode::FilesWithMatches
+        } else if self.files_without_match {
+            OutputMode::FilesWithoutMatch
+        } else if self.quiet {
+            OutputMode::Quiet
+        } else {
+            OutputMode::Lines
+        };
+        let layout = if self.vimgrep {
+            LineLayout::Vimgrep
+        } else if self.heading {
+            LineLayout::Heading
+        } else if self.no_heading || self.format.is_some() {
+            // Templates replace the whole line, so they build on the flat layout
+            LineLayout::NoHeading
+        } else {
+            LineLayout::Verbose
+        };
+        Ok(SearchOptions {
+            ignore_case: self.ignore_case,
+            smart_case: self.smart_case,
+            mode,
+            layout,
+            null_separator: self.null,
+            invert_match: self.invert_match,
+            max_count: self.max_count,
+            // The first match decides the exit status, so quiet runs stop there
+            max_total: if self.quiet { Some(1) } else { self.max_total },
+            word_regexp: self.word_regexp,
+            line_regexp: self.line_regexp,
+            column_unit: self.column_unit.unwrap_or(layout.default_column_unit()),
+            wrap: WrapOptions {
+                max_columns: self.max_columns,
+                max_columns_preview: self.max_columns_preview,
+                no_truncate: self.no_truncate,
+                context_chars: self.context_chars,
+            },
+            walk: self.walk.walk_options(),
+            search_zip: self.search_zip,
+            search_archives: self.search_archives,
+            archive: self.archive_options()?,
+            fail_on_read_error: self.fail_on_read_error,
+            templates: self.format.clone().map(|line| OutputTemplates {
+                line,
+                file_header: self.file_header.clone(),
+                file_footer: self.file_footer.clone(),
+                header: self.header.clone(),
+                footer: self.footer.clone(),
+            }),
+            no_progress: self.no_progress,
+            stats: self.stats,
+            // Only the HTML report shows context, so other runs skip collecting it
+            context_lines: match self.report_format() {
+                Some(OutputFormat::Html) => self.html_context,
+                _ => 0,
+            },
+        })
+    }
+
+    /// Format of the report: --output-format, else guessed from the --output
+    /// file name (text when unknown)
+    fn report_format(&self) -> Option<OutputFormat> {
+        match &self.output {
+            Some(spec) => Some(
+                self.output_format
+                    .or_else(|| OutputFormat::from_extension(Path::new(spec)))
+                    .unwrap_or(OutputFormat::Text),
+            ),
+            None => self.output_format,
+        }
+    }
+
+    fn report_options(&self) -> Result<ReportOptions> {
+        let csv = CsvOptions {
+            delimiter: self.csv_delimiter,
+            columns: if self.csv_columns.is_empty() {
+                CsvColumn::ALL.to_vec()
+            } else {
+                self.csv_columns.clone()
+            },
+        };
+        let sarif = SarifOptions {
+            rule_id: self.sarif_rule_id.clone(),
+            level: self.sarif_level,
+        };
+        let Some(spec) = &self.output else {
+            return Ok(ReportOptions {
+                file: None,
+                format: self.output_format,
+                append: false,
+                csv,
+                sarif,
+            });
+        };
+        let format = self.report_format().unwrap_or(OutputFormat::Text);
+        if self.append && !format.can_append() {
+            return Err(anyhow!(tr_with("append_unsupported", &[("format", &format.extension())])));
+        }
+        Ok(ReportOptions {
+            file: Some(output::resolve_path(spec, &self.query, format)),
+            format: Some(format),
+            append: self.append,
+            csv,
+            sarif,
+        })
+    }
+
+    fn archive_options(&self) -> Result<ArchiveOptions> {
+        let member_glob = if self.archive_glob.is_empty() {
+            None
+        } else {
+            let mut builder = GlobSetBuilder::new();
+            for glob in &self.archive_glob {
+                builder.add(glob.clone());
+            }
+            Some(builder.build()?)
         };
-        let mut file = File::new(
-            file_path.to_string_lossy().to_string(),
-            file::Format::Text,
-            content,
-        );
-        let display_list = find_content_in_file(&query, &mut file, ignore_case)?;
-        display_map.insert(file.name.clone(), display_list);
+        Ok(ArchiveOptions {
+            max_depth: self.max_archive_depth,
+            member_glob,
+            max_member_size: self.max_member_size,
+        })
+    }
+}
+
+#[derive(Args)]
+struct FilesArgs {
+    #[arg(short, long, required = true)]
+    query: String,
+
+    /// Directories to search (defaults to the current directory)
+    #[arg(value_name = "PATH")]
+    paths: Vec<String>,
+
+    /// Interpret the query as a glob pattern
+    #[arg(short, long, group = "pattern")]
+    glob: bool,
+
+    /// Interpret the query as a regular expression
+    #[arg(short = 'e', long, group = "pattern")]
+    regex: bool,
+
+    /// Match against the path relative to the searched directory instead of the file name
+    #[arg(short = 'p', long)]
+    full_path: bool,
+
+    #[arg(short, long)]
+    ignore_case: bool,
+
+    /// Terminate printed paths with a NUL byte instead of a newline
+    #[arg(short = '0', long)]
+    null: bool,
+
+    #[command(flatten)]
+    exec: ExecArgs,
+
+    #[command(flatten)]
+    walk: WalkArgs,
+}
+
+fn handle_files(args: FilesArgs, paging: Paging) -> Result<i32> {
+    let matcher = if args.glob {
+        NameMatcher::glob(&args.query, args.ignore_case)?
+    } else if args.regex {
+        NameMatcher::regex(&args.query, args.ignore_case)?
+    } else {
+        NameMatcher::literal(&args.query, args.ignore_case)
+    };
+    let walk_options = args.walk.walk_options();
+    let roots = if args.paths.is_empty() {
+        vec![".".to_string()]
+    } else {
+        args.paths
+    };
+
+    // Paths are kept relative to the given roots, like `find` and `fd` print them
+    let mut matches: Vec<(String, Vec<(usize, usize)>)> = Vec::new();
+    for root in roots {
+        let root_path = PathBuf::from(&root);
+        if !root_path.exists() {
+            warn(&tr_with("path_missing", &[("path", &root)]));
+            continue;
+        }
+        for file in walk_options.collect_files(vec![root_path.clone()]).0 {
+            let display_path = file.to_string_lossy().to_string();
+            let display_path = display_path.strip_prefix("./").unwrap_or(&display_path).to_string();
+            let file_name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let text = match file.strip_prefix(&root_path) {
+                Ok(relative) if args.full_path && !relative.as_os_str().is_empty() => {
+                    relative.to_string_lossy().to_string()
+                }
+                _ => file_name,
+            };
+
+            let Some(spans) = matcher.find(&text) else {
+                continue;
+            };
+            // The matched text is always a suffix of the printed path
+            let offset = display_path.len() - text.len();
+            let spans = spans.iter().map(|&(start, end)| (start + offset, end + offset)).collect();
+            matches.push((display_path, spans));
+        }
+    }
+    matches.sort();
+    matches.dedup_by(|a, b| a.0 == b.0);
+    if matches.is_empty() {
+        return Ok(EXIT_NO_MATCH);
+    }
+
+    if args.exec.is_enabled() {
+        let targets = matches.into_iter().map(|(path, _)| ExecTarget::path(path)).collect();
+        return args.exec.run(targets);
+    }
+
+    let mut output = String::new();
+    for (path, spans) in matches {
+        if args.null {
+            output.push_str(&format!("{}\0", path));
+        } else {
+            output.push_str(&format!("{}\n", format::highlight_spans(&path, &spans)));
+        }
+    }
+    // NUL-separated paths are meant for other programs, not for a pager
+    let paging = if args.null { Paging::Never } else { paging };
+    paging.print(&output)?;
+    Ok(EXIT_MATCH)
+}
+
+fn handle_paths(
+    query: String,
+    paths: Vec<String>,
+    options: SearchOptions,
+    report: ReportOptions,
+    exec: ExecArgs,
+    paging: Paging,
+) -> Result<i32> {
+    let valid_paths = find_valid_paths(paths)?;
+
+    // Use parallel processing
+    let started = Instant::now();
+    let mut processor = ParallelProcessor::new(query.clone(), options.clone());
+    let results = processor.process_paths(valid_paths)?;
+    let elapsed = started.elapsed();
+
+    // Convert DashMap to HashMap for output
+    let mut display_map: HashMap<String, Vec<DisPlay>> = HashMap::new();
+    for entry in results.iter() {
+        display_map.insert(entry.key().clone(), entry.value().to_vec());
+    }
+
+    let found = display_map
+        .values()
+        .any(|displays| options.mode.reports_file(!displays.is_empty()));
+
+    if exec.is_enabled() {
+        if !found {
+            return Ok(EXIT_NO_MATCH);
+        }
+        return exec.run(exec_targets(&display_map, &options));
     }
-    let output = get_output(display_map.clone(), false);
-    println!("{}", output);
-    if let Some(out_dir) = out_dir {
-        let out_dir = PathBuf::from(out_dir);
-        let canonical_dir = out_dir.canonicalize()?;
-        let output_file = canonical_dir.join("output.txt");
-        let output = get_output(display_map, true);
-        fs::write(&output_file, output)?;
-        println!("Output saved to: {:?}", output_file);
+    if options.mode != OutputMode::Quiet {
+        let output = terminal_output(&display_map, &options, &query, &report);
+        paging.print(&output)?;
+        write_report_file(&display_map, &options, &query, &report)?;
+    }
+    if options.stats {
+        eprint!("{}", processor.stats().summary(&result_counts(&display_map, &options), elapsed));
+    }
+    Ok(if found { EXIT_MATCH } else { EXIT_NO_MATCH })
+}
+
+/// Reported files, lines and match occurrences, for --stats
+fn result_counts(display_map: &HashMap<String, Vec<DisPlay>>, options: &SearchOptions) -> ResultCounts {
+    let reported = display_map
+        .values()
+        .filter(|displays| options.mode.reports_file(!displays.is_empty()));
+    ResultCounts {
+        files: reported.clone().count(),
+        lines: reported.clone().map(|displays| displays.len()).sum(),
+        matches: reported.flatten().map(|display| display.match_count()).sum(),
+    }
+}
+
+/// The files a `find` run reports, sorted by path, as `--exec` targets.
+/// Standard input and archive members have no path a command could open,
+/// so they are skipped with a warning.
+fn exec_targets(display_map: &HashMap<String, Vec<DisPlay>>, options: &SearchOptions) -> Vec<ExecTarget> {
+    let mut targets: Vec<ExecTarget> = display_map
+        .iter()
+        .filter(|(_, displays)| options.mode.reports_file(!displays.is_empty()))
+        .filter(|(file_path, _)| {
+            // A real path may contain the member separator as well
+            let is_member = file_path.contains(MEMBER_SEPARATOR) && !Path::new(file_path).exists();
+            if file_path.as_str() == STDIN_LABEL || is_member {
+                warn(&tr_with("exec_not_a_file", &[("path", file_path)]));
+                return false;
+            }
+            true
+        })
+        .map(|(file_path, displays)| {
+            let first = displays.first();
+            ExecTarget {
+                path: file_path.clone(),
+                line: first.map(|display| display.line_number()),
+                column: first.and_then(|display| display.columns().first().map(|&(start, _)| start)),
+            }
+        })
+        .collect();
+    targets.sort_by(|a, b| a.path.cmp(&b.path));
+    targets
+}
+
+/// The results as printed in the terminal
+fn terminal_output(
+    display_map: &HashMap<String, Vec<DisPlay>>,
+    options: &SearchOptions,
+    query: &str,
+    report: &ReportOptions,
+) -> String {
+    if let (None, Some(format)) = (&report.file, report.format) {
+        render_report(display_map, options, query, format, report, true)
+    } else if options.mode == OutputMode::Lines && options.layout != LineLayout::Verbose {
+        get_layout_output(display_map, options, query, false)
+    } else if options.mode == OutputMode::Lines {
+        let output = get_output(display_map.clone(), options, false);
+        let line_count: usize = display_map.values().map(|displays| displays.len()).sum();
+        let file_count = display_map.values().filter(|displays| !displays.is_empty()).count();
+        let summary = tr_with("match_summary", &[("lines", &line_count), ("files", &file_count)]);
+        format!("{}\n{}\n", output, summary)
+    } else {
+        // Paths and numbers only, so the output can be piped into other tools
+        get_summary_output(display_map, options)
+    }
+}
+
+/// Writes the --output file, if any
+fn write_report_file(
+    display_map: &HashMap<String, Vec<DisPlay>>,
+    options: &SearchOptions,
+    query: &str,
+    report: &ReportOptions,
+) -> Result<()> {
+    if let Some(file) = &report.file {
+        let format = report.format.unwrap_or(OutputFormat::Text);
+        // Appended CSV rows go below the header that is already there
+        let with_header = !(report.append && output::has_content(file));
+        let content = render_report(display_map, options, query, format, report, with_header);
+        output::write_atomic(file, &content, report.append)?;
+        eprintln!("{}", tr_with("output_saved", &[("path", &file.display())]));
     }
     Ok(())
 }
 
-fn get_output(display_map: HashMap<String, Vec<DisPlay>>, pure_text_output: bool) -> String {
+/// Renders the results in a report file format, without colors
+fn render_report(
+    display_map: &HashMap<String, Vec<DisPlay>>,
+    options: &SearchOptions,
+    query: &str,
+    format: OutputFormat,
+    report_options: &ReportOptions,
+    with_header: bool,
+) -> String {
+    let report = Report::new(query, display_map, options);
+    match format {
+        OutputFormat::Text if options.mode == OutputMode::Lines && options.layout != LineLayout::Verbose => {
+            get_layout_output(display_map, options, query, true)
+        }
+        OutputFormat::Text if options.mode == OutputMode::Lines => get_output(display_map.clone(), options, true),
+        OutputFormat::Text => get_summary_output(display_map, options),
+        OutputFormat::Json => report.json(),
+        OutputFormat::Csv | OutputFormat::Tsv => report.csv(format, &report_options.csv, with_header),
+        OutputFormat::Markdown => report.markdown(),
+        OutputFormat::Html => html::render(&report, Local::now()),
+        OutputFormat::Sarif => sarif::render(&report, &report_options.sarif),
+    }
+}
+
+fn get_output(display_map: HashMap<String, Vec<DisPlay>>, options: &SearchOptions, pure_text_output: bool) -> String {
     let mut output = String::new();
     display_map.iter().for_each(|(file_path, displays)| {
         let file_name_vec: Vec<&str> = file_path.rsplit('\\').collect();
@@ -99,20 +854,19 @@ fn get_output(display_map: HashMap<String, Vec<DisPlay>>, pure_text_output: bool
         if !displays.is_empty() {
             if !pure_text_output {
                 let tip = format!(
-                    "Found the following matches in file '{}': \n",
-                    file_name.yellow()
+                    "{}\n",
+                    tr_with("matches_in_file", &[("file", &paint(Role::Path, &file_name))])
                 );
                 output.push_str(&tip);
             } else {
-                let tip = format!("Found the following matches in file '{}': \n", file_name);
+                let tip = format!("{}\n", tr_with("matches_in_file", &[("file", &file_name)]));
                 output.push_str(&tip);
             }
             for display in displays {
-                let mut out_line: String = String::new();
-                if pure_text_output {
-                    out_line = String::from(display.pure_display());
+                let out_line = if pure_text_output {
+                    display.pure_display(&options.wrap)
                 } else {
-                    out_line = String::from(display.display());
+                    display.display(&options.wrap)
                 };
                 output.push_str(out_line.as_str());
             }
@@ -121,6 +875,96 @@ fn get_output(display_map: HashMap<String, Vec<DisPlay>>, pure_text_output: bool
     output
 }
 
+/// Builds the output of the --heading, --no-heading, --vimgrep and --format
+/// layouts, sorted by path
+fn get_layout_output(
+    display_map: &HashMap<String, Vec<DisPlay>>,
+    options: &SearchOptions,
+    query: &str,
+    pure_text_output: bool,
+) -> String {
+    if let Some(templates) = &options.templates {
+        let report = Report::new(query, display_map, options);
+        return template::render(&report, templates, !pure_text_output && color::enabled());
+    }
+
+    let mut file_paths: Vec<&String> = display_map.keys().collect();
+    file_paths.sort();
+
+    let mut output = String::new();
+    for file_path in file_paths {
+        let displays = &display_map[file_path];
+        if displays.is_empty() {
+            continue;
+        }
+        let path = if pure_text_output {
+            file_path.to_string()
+        } else {
+            paint(Role::Path, file_path)
+        };
+        match options.layout {
+            LineLayout::Heading => {
+                // Files are separated by an empty line
+                if !output.is_empty() {
+                    output.push('\n');
+                }
+                output.push_str(&format!("{}\n", path));
+                for display in displays {
+                    output.push_str(&format!("{}\n", display.compact_display(&options.wrap, pure_text_output)));
+                }
+            }
+            // Editors parse these records, so they are never colored
+            LineLayout::Vimgrep => {
+                for display in displays {
+                    for record in display.vimgrep_records() {
+                        output.push_str(&format!("{}:{}\n", file_path, record));
+                    }
+                }
+            }
+            LineLayout::NoHeading | LineLayout::Verbose => {
+                for display in displays {
+                    output.push_str(&format!("{}:{}\n", path, display.compact_display(&options.wrap, pure_text_output)));
+                }
+            }
+        }
+    }
+    output
+}
+
+/// Builds the output of the count and file-list modes: one record per file,
+/// sorted by path, with the path separated from the count by `:` or NUL.
+fn get_summary_output(display_map: &HashMap<String, Vec<DisPlay>>, options: &SearchOptions) -> String {
+    let mut file_paths: Vec<&String> = display_map.keys().collect();
+    file_paths.sort();
+
+    let mut output = String::new();
+    for file_path in file_paths {
+        let displays = &display_map[file_path];
+        let count = match options.mode {
+            OutputMode::Count => displays.len(),
+            // Inverted lines carry no occurrences, so count the lines themselves
+            OutputMode::CountMatches if options.invert_match => displays.len(),
+            OutputMode::CountMatches => displays.iter().map(|d| d.match_count()).sum(),
+            OutputMode::FilesWithMatches if !displays.is_empty() => 0,
+            OutputMode::FilesWithoutMatch if displays.is_empty() => 0,
+            _ => continue,
+        };
+
+        output.push_str(file_path);
+        match options.mode {
+            OutputMode::Count | OutputMode::CountMatches => {
+                output.push(if options.null_separator { '\0' } else { ':' });
+                output.push_str(&format!("{}\n", count));
+            }
+            _ => output.push(if options.null_separator { '\0' } else { '\n' }),
+        }
+    }
+    output
+}
+
+/// Canonicalizes the given files and directories, dropping duplicates and
+/// any path that lies inside another given directory so that overlapping
+/// trees are only searched once.
 fn find_valid_paths(file_paths: Vec<String>) -> Result<Vec<PathBuf>> {
     let mut unique_paths = HashSet::new();
     let mut valid_paths = Vec::new();
@@ -128,20 +972,28 @@ fn find_valid_paths(file_paths: Vec<String>) -> Result<Vec<PathBuf>> {
     for file_path in file_paths {
         let path = PathBuf::from(&file_path);
 
+        // Standard input has no canonical path
+        if file_path == STDIN_PATH {
+            if unique_paths.insert(path.clone()) {
+                valid_paths.push(path);
+            }
+            continue;
+        }
+
         if !path.exists() {
-            eprintln!("Warning: '{}' does not exist", file_path);
+            warn(&tr_with("path_missing", &[("path", &file_path)]));
             continue;
         }
 
-        if !path.is_file() {
-            eprintln!("Warning: '{}' is not a valid file", file_path);
+        if !path.is_file() && !path.is_dir() {
+            warn(&tr_with("path_invalid", &[("path", &file_path)]));
             continue;
         }
 
         let canonical_path = match path.canonicalize() {
             Ok(p) => p,
             Err(e) => {
-                eprintln!("Warning: Cannot canonicalize path '{}': {}", file_path, e);
+                warn(&tr_with("path_canonicalize", &[("path", &file_path), ("error", &e)]));
                 continue;
             }
         };
@@ -152,192 +1004,124 @@ fn find_valid_paths(file_paths: Vec<String>) -> Result<Vec<PathBuf>> {
     }
 
     if valid_paths.is_empty() {
-        return Err(anyhow!("No valid files found"));
+        return Err(anyhow!(tr("no_valid_paths")));
     }
 
+    // Paths inside another given directory are kept: depth limits may keep
+    // the walk from reaching them, and the walk dedupes the files it finds
     Ok(valid_paths)
 }
 
-fn find_content_in_file(
-    query: &String,
-    file: &mut File,
-    ignore_case: bool,
-) -> Result<Vec<DisPlay>> {
-    let mut display_list = Vec::new();
-    let file_name = file.name.clone();
-    let query_str = query.as_str();
-
-    let mut line_index = 0;
-    while let Some(line) = file.next_line() {
-        let ori_query = query_str.to_string();
-        let ori_line = line.clone();
-
-        // Convert to lowercase if ignoring case
-        let line = if ignore_case {
-            line.to_lowercase()
-        } else {
-            line.clone()
-        };
-
-        let query_str = if ignore_case {
-            query_str.to_lowercase()
-        } else {
-            query_str.to_string()
-        };
-
-        if !line.contains(&query_str) {
-            line_index += 1;
-            continue;
-        }
-
-        let mut match_indices = Vec::new();
-        let mut matched_str = "";
-
-        for (start, matched) in line.match_indices(&query_str) {
-            match_indices.push(start);
-            matched_str = matched;
-        }
-
-        let display = DisPlay::new(
-            ori_query,
-            file_name.clone(),
-            line_index,
-            match_indices,
-            ori_line,
-            matched_str.to_string(),
-        );
-        display_list.push(display);
-        line_index += 1;
-    }
-
-    Ok(display_list)
-}
-
-fn handle_dir_vec(
-    query: String,
-    dir_paths: Vec<String>,
-    ignore_case: bool,
-    out_dir: Option<String>,
-) -> Result<()> {
-    if dir_paths.len() > 1 {
-        return Err(anyhow!("Only one directory path can be specified"));
-    }
-    let dir_path = dir_paths
-        .first()
-        .ok_or_else(|| anyhow!("Must provide a directory path"))?;
-    let valid_file_path = find_valid_dirs(dir_path.clone())?;
-
-    // Use parallel processing
-    let mut processor = ParallelProcessor::new(query);
-    let results = processor.process_directory(valid_file_path, ignore_case)?;
-
-    // Convert DashMap to HashMap for output
-    let mut display_map: HashMap<String, Vec<DisPlay>> = HashMap::new();
-    for entry in results.iter() {
-        display_map.insert(entry.key().clone(), entry.value().to_vec());
-    }
-
-    let output = get_output(display_map.clone(), false);
-    println!("{}", output);
-    if let Some(out_dir) = out_dir {
-        let out_dir = PathBuf::from(out_dir);
-        let canonical_dir = out_dir.canonicalize()?;
-        let output_file = canonical_dir.join("output.txt");
-        let output = get_output(display_map, true);
-        fs::write(&output_file, output)?;
-        println!("Output saved to: {:?}", output_file);
-    }
-    Ok(())
-}
-
-fn find_valid_dirs(dir_path: String) -> Result<PathBuf> {
-    let path = PathBuf::from(&dir_path);
-    if !path.exists() {
-        return Err(anyhow!("Directory '{}' does not exist", dir_path));
-    }
-    if !path.is_dir() {
-        return Err(anyhow!("Path '{}' is not a valid directory", dir_path));
-    }
-    let canonical_path = match path.canonicalize() {
-        Ok(p) => p,
-        Err(e) => {
-            return Err(anyhow!(
-                "Warning: Cannot canonicalize path '{}': {}",
-                dir_path,
-                e
-            ));
-        }
+/// Reads a list of paths for `--files-from`. Entries are NUL-separated when
+/// the list contains a NUL byte (`git ls-files -z`, `find -print0`) and
+/// newline-separated otherwise.
+fn read_files_from(list_path: &str) -> Result<Vec<String>> {
+    let content = if list_path == STDIN_PATH {
+        io::read_to_string(io::stdin())?
+    } else {
+        fs::read_to_string(list_path)
+            .map_err(|e| anyhow!(tr_with("cannot_read_path_list", &[("path", &list_path), ("error", &e)])))?
     };
 
-    Ok(canonical_path)
+    let entries: Vec<String> = if content.contains('\0') {
+        content.split('\0').map(|s| s.to_string()).collect()
+    } else {
+        content.lines().map(|s| s.trim_end_matches('\r').to_string()).collect()
+    };
+    Ok(entries.into_iter().filter(|s| !s.is_empty()).collect())
 }
 
-fn handle_diff(file1: String, file2: String) -> Result<()> {
-    let content1 = fs::read_to_string(PathBuf::from(file1))?;
-    let content2 = fs::read_to_string(PathBuf::from(file2))?;
+fn handle_diff(file1: String, file2: String, paging: Paging) -> Result<()> {
+    let content1 = fs::read_to_string(PathBuf::from(&file1))?;
+    let content2 = fs::read_to_string(PathBuf::from(&file2))?;
 
     let diff = TextDiff::from_lines(&content1, &content2);
 
+    let mut output = format!("{}\n", tr_with("comparing_files", &[("file1", &file1), ("file2", &file2)]));
     for change in diff.iter_all_changes() {
         let sign = match change.tag() {
             ChangeTag::Delete => "-",
             ChangeTag::Insert => "+",
             ChangeTag::Equal => " ",
         };
-        print!("{}{}", sign, change);
+        output.push_str(&format!("{}{}", sign, change));
     }
-    Ok(())
+    paging.print(&output)
 }
 
-fn main() -> Result<()> {
+/// Exit status when something matched, as with grep
+const EXIT_MATCH: i32 = 0;
+/// Exit status when nothing matched
+const EXIT_NO_MATCH: i32 = 1;
+/// Exit status for errors, including a failing --exec command
+const EXIT_ERROR: i32 = 2;
+
+fn main() -> ExitCode {
+    let code = match run() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", tr_with("error", &[("message", &format!("{:?}", e))]));
+            EXIT_ERROR
+        }
+    };
+    ExitCode::from(u8::try_from(code).unwrap_or(EXIT_ERROR as u8))
+}
+
+/// Runs the selected subcommand and returns the process exit code
+fn run() -> Result<i32> {
     let cli = Cli::parse();
+    i18n::init(cli.lang.unwrap_or_else(Lang::from_env));
+    color::init(cli.color.palette());
+    let paging = cli.pager.paging();
 
     match cli.command {
-        Some(Commands::Find {
-            query,
-            file_path,
-            dir,
-            ignore_case,
-            output,
-        }) => match (file_path.is_empty(), dir.is_empty()) {
-            (false, false) => {
-                return Err(anyhow!(
-                    "Can only specify one of file_path or dir, not both"
-                ));
-            }
-            (true, true) => {
-                return Err(anyhow!("Must specify either file_path or dir"));
-            }
-            (false, true) => {
-                println!("Searching in files {:?}", file_path);
-                if let Some(out_dir) = output.clone() {
-                    if !PathBuf::from(&out_dir).exists() {
-                        return Err(anyhow!("Output directory does not exist"));
-                    }
+        Some(Commands::Find(args)) => {
+            let options = args.search_options()?;
+            let report = args.report_options()?;
+            let FindArgs {
+                query,
+                paths,
+                file_path,
+                dir,
+                files_from,
+                exec,
+                ..
+            } = *args;
+            // --file-path and --dir are kept as aliases of the positional paths
+            let mut paths: Vec<String> = paths.into_iter().chain(file_path).chain(dir).collect();
+            if let Some(list_path) = files_from.as_ref() {
+                paths.extend(read_files_from(list_path)?);
+                // Standard input can only be read once
+                if list_path == STDIN_PATH && paths.iter().any(|path| path == STDIN_PATH) {
+                    return Err(anyhow!(tr("stdin_used_twice")));
                 }
-                handle_file_path_vec(query, file_path, ignore_case, output)?;
+            } else if paths.is_empty() && !io::stdin().is_terminal() {
+                // Search piped input when no path is given
+                paths.push(STDIN_PATH.to_string());
             }
-            (true, false) => {
-                println!("Searching in directory {:?}", dir);
-                if let Some(out_dir) = output.clone() {
-                    if !PathBuf::from(&out_dir).exists() {
-                        return Err(anyhow!("Output directory does not exist"));
-                    }
-                }
-                handle_dir_vec(query, dir, ignore_case, output)?;
+            if paths.is_empty() {
+                return Err(anyhow!(tr("no_search_paths")));
             }
-        },
+            let prints_lines = report.file.is_some() || report.format.is_none();
+            let verbose = options.mode == OutputMode::Lines && options.layout == LineLayout::Verbose;
+            if verbose && prints_lines && !exec.is_enabled() {
+                eprintln!("{}", tr_with("searching_in", &[("paths", &format!("{:?}", paths))]));
+            }
+            return handle_paths(query, paths, options, report, exec, paging);
+        }
+        Some(Commands::Files(args)) => {
+            return handle_files(*args, paging);
+        }
         Some(Commands::Diff { file1, file2 }) => {
-            println!("Comparing files {} and {}", file1, file2);
             if !PathBuf::from(file1.clone()).is_file() || !PathBuf::from(file2.clone()).is_file() {
-                return Err(anyhow!("Both files must exist"));
+                return Err(anyhow!(tr("diff_files_missing")));
             }
-            handle_diff(file1, file2)?;
+            handle_diff(file1, file2, paging)?;
         }
         None => {
-            return Err(anyhow!("Please specify a subcommand: find or diff"));
+            return Err(anyhow!(tr("missing_subcommand")));
         }
     }
 
-    Ok(())
+    Ok(EXIT_MATCH)
 }
@@ -0,0 +1,236 @@
+use crate::options::SearchOptions;
+
+/// Finds the occurrences of a query in a line, honouring the case and
+/// word/line matching options of a search.
+pub struct Matcher {
+    query: String,
+    ignore_case: bool,
+    word_regexp: bool,
+    line_regexp: bool,
+}
+
+impl Matcher {
+    pub fn new(query: &str, options: &SearchOptions) -> Self {
+        // --smart-case 只在查询不含大写字母时忽略大小写
+        let ignore_case =
+            options.ignore_case || (options.smart_case && !query.chars().any(char::is_uppercase));
+        let query = if ignore_case {
+            FoldedLine::new(query).text
+        } else {
+            query.to_string()
+        };
+        Matcher {
+            query,
+            ignore_case,
+            word_regexp: options.word_regexp,
+            line_regexp: options.line_regexp,
+        }
+    }
+
+    /// Returns the `(start, end)` byte spans of the matches in `line`.
+    pub fn find_matches(&self, line: &str) -> Vec<(usize, usize)> {
+        if self.query.is_empty() {
+            return Vec::new();
+        }
+
+        let folded = self.ignore_case.then(|| FoldedLine::new(line));
+        let haystack = folded.as_ref().map_or(line, |folded| folded.text.as_str());
+        let mut spans = Vec::new();
+        let mut from = 0;
+        while let Some(found) = haystack[from..].find(&self.query) {
+            let start = from + found;
+            let end = start + self.query.len();
+            let span = match &folded {
+                Some(folded) => folded.original_span(start, end),
+                None => Some((start, end)),
+            };
+            match span {
+                Some(span) if self.is_accepted(line, span.0, span.1) => {
+                    spans.push(span);
+                    from = end;
+                }
+                // 被拒绝的候选之内可能还有重叠的候选，从下一个字符继续查找
+                _ => from = start + haystack[start..].chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        spans
+    }
+
+    fn is_accepted(&self, line: &str, start: usize, end: usize) -> bool {
+        if self.line_regexp {
+            return start == 0 && end == line.len();
+        }
+        if self.word_regexp {
+            // 与 grep -w 一致：匹配前后都不能紧邻单词字符
+            let before = line[..start].chars().next_back();
+            let after = line[end..].chars().next();
+            return !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char);
+        }
+        true
+    }
+}
+
+/// Unicode word constituent: letters, digits and the underscore
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// A case-folded copy of a line that remembers, for every folded byte, the
+/// byte offset of the original character it was produced from.
+///
+/// Folding can change the byte length of a character (`ẞ` is three bytes,
+/// its folded form `ss` is two), so offsets found in the folded text can not
+/// be used on the original line directly.
+struct FoldedLine {
+    text: String,
+    offsets: Vec<usize>,
+    original_len: usize,
+}
+
+impl FoldedLine {
+    fn new(line: &str) -> Self {
+        let mut text = String::with_capacity(line.len());
+        let mut offsets = Vec::with_capacity(line.len());
+        for (offset, c) in line.char_indices() {
+            let folded_start = text.len();
+            fold_char(c, &mut text);
+            offsets.resize(offsets.len() + text.len() - folded_start, offset);
+        }
+        FoldedLine {
+            text,
+            offsets,
+            original_len: line.len(),
+        }
+    }
+
+    /// Maps a span of the folded text back to the original line. Spans that
+    /// start or end inside the expansion of a single character (e.g. `s`
+    /// matching half of the `ss` folded from `ß`) have no original
+    /// counterpart and yield `None`.
+    fn original_span(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        if !self.is_char_boundary(start) || !self.is_char_boundary(end) {
+            return None;
+        }
+        let original_start = self.offsets[start];
+        let original_end = self.offsets.get(end).copied().unwrap_or(self.original_len);
+        Some((original_start, original_end))
+    }
+
+    fn is_char_boundary(&self, index: usize) -> bool {
+        index == 0 || index == self.offsets.len() || self.offsets[index - 1] != self.offsets[index]
+    }
+}
+
+/// Case-folds a single character, using the full folding of `ß`/`ẞ` and
+/// mapping the final sigma and the dotted capital I onto their plain forms
+/// so that e.g. `STRASSE`/`Straße`, `ΟΔΟΣ`/`οδος` and `İstanbul`/`istanbul`
+/// compare equal.
+fn fold_char(c: char, out: &mut String) {
+    match c {
+        'ß' | 'ẞ' => out.push_str("ss"),
+        'ς' => out.push('σ'),
+        'İ' => out.push('i'),
+        _ => out.extend(c.to_lowercase()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(query: &str, word_regexp: bool, line_regexp: bool) -> Matcher {
+        let options = SearchOptions {
+            word_regexp,
+            line_regexp,
+            ..Default::default()
+        };
+        Matcher::new(query, &options)
+    }
+
+    fn ignore_case_matcher(query: &str) -> Matcher {
+        let options = SearchOptions {
+            ignore_case: true,
+            ..Default::default()
+        };
+        Matcher::new(query, &options)
+    }
+
+    /// The matched text of every span, taken from the original line
+    fn matched<'a>(matcher: &Matcher, line: &'a str) -> Vec<&'a str> {
+        matcher
+            .find_matches(line)
+            .into_iter()
+            .map(|(start, end)| &line[start..end])
+            .collect()
+    }
+
+    #[test]
+    fn test_word_regexp() {
+        let matcher = matcher("id", true, false);
+        assert_eq!(matcher.find_matches("valid identity idle"), vec![]);
+        assert_eq!(matcher.find_matches("let id = user.id;"), vec![(4, 6), (14, 16)]);
+        assert_eq!(matcher.find_matches("id_x id"), vec![(5, 7)]);
+        assert_eq!(matcher.find_matches("名id"), vec![]);
+        assert_eq!(matcher.find_matches("«id»"), vec![(2, 4)]);
+    }
+
+    #[test]
+    fn test_word_regexp_overlapping() {
+        // Candidates overlapping a rejected one are still tried
+        assert_eq!(matcher("aa", true, false).find_matches("aaa aa"), vec![(4, 6)]);
+        assert_eq!(matcher("x-x", true, false).find_matches("yx-x-x"), vec![(3, 6)]);
+        let options = SearchOptions {
+            ignore_case: true,
+            word_regexp: true,
+            ..Default::default()
+        };
+        assert_eq!(Matcher::new("x-x", &options).find_matches("YX-X-X"), vec![(3, 6)]);
+    }
+
+    #[test]
+    fn test_line_regexp() {
+        let matcher = matcher("abc", false, true);
+        assert_eq!(matcher.find_matches("abc"), vec![(0, 3)]);
+        assert_eq!(matcher.find_matches("abc abc"), vec![]);
+        assert_eq!(matcher.find_matches(" abc"), vec![]);
+    }
+
+    #[test]
+    fn test_ignore_case_turkish() {
+        let matcher = ignore_case_matcher("istanbul");
+        assert_eq!(matched(&matcher, "İSTANBUL, İstanbul"), vec!["İSTANBUL", "İstanbul"]);
+        // The offsets must point into the original line, after the two-byte `İ`
+        let matcher = ignore_case_matcher("abc");
+        assert_eq!(matcher.find_matches("İİ abc"), vec![(5, 8)]);
+    }
+
+    #[test]
+    fn test_ignore_case_german() {
+        let matcher = ignore_case_matcher("strasse");
+        assert_eq!(matched(&matcher, "STRASSE Straße STRAẞE"), vec!["STRASSE", "Straße", "STRAẞE"]);
+        // `ẞ` shrinks from three bytes to two when folded
+        let matcher = ignore_case_matcher("x");
+        assert_eq!(matcher.find_matches("ẞẞ x"), vec![(7, 8)]);
+        // Half of a folded `ß` is not a match
+        let matcher = ignore_case_matcher("s");
+        assert_eq!(matched(&matcher, "ß S"), vec!["S"]);
+    }
+
+    #[test]
+    fn test_ignore_case_greek() {
+        let matcher = ignore_case_matcher("οδος");
+        assert_eq!(matched(&matcher, "ΟΔΟΣ, οδος, Οδός"), vec!["ΟΔΟΣ", "οδος"]);
+        let matcher = ignore_case_matcher("Σ");
+        assert_eq!(matched(&matcher, "σς"), vec!["σ", "ς"]);
+    }
+
+    #[test]
+    fn test_smart_case() {
+        let options = SearchOptions {
+            smart_case: true,
+            ..Default::default()
+        };
+        assert_eq!(Matcher::new("error", &options).find_matches("Error error"), vec![(0, 5), (6, 11)]);
+        assert_eq!(Matcher::new("Error", &options).find_matches("Error error"), vec![(0, 5)]);
+    }
+}
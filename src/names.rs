@@ -0,0 +1,75 @@
+use anyhow::Result;
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+
+use crate::matcher::Matcher;
+use crate::options::SearchOptions;
+
+/// Matches file names or paths for the `files` subcommand
+pub enum NameMatcher {
+    Literal(Matcher),
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl NameMatcher {
+    pub fn literal(query: &str, ignore_case: bool) -> Self {
+        let options = SearchOptions {
+            ignore_case,
+            ..Default::default()
+        };
+        NameMatcher::Literal(Matcher::new(query, &options))
+    }
+
+    pub fn glob(query: &str, ignore_case: bool) -> Result<Self> {
+        let glob = GlobBuilder::new(query)
+            .case_insensitive(ignore_case)
+            .literal_separator(true)
+            .build()?;
+        Ok(NameMatcher::Glob(glob.compile_matcher()))
+    }
+
+    pub fn regex(query: &str, ignore_case: bool) -> Result<Self> {
+        let regex = RegexBuilder::new(query).case_insensitive(ignore_case).build()?;
+        Ok(NameMatcher::Regex(regex))
+    }
+
+    /// Returns the byte spans to highlight in `text`, or `None` if it does not
+    /// match. A glob always highlights the whole text.
+    pub fn find(&self, text: &str) -> Option<Vec<(usize, usize)>> {
+        match self {
+            NameMatcher::Literal(matcher) => {
+                let spans = matcher.find_matches(text);
+                (!spans.is_empty()).then_some(spans)
+            }
+            NameMatcher::Glob(glob) => glob.is_match(text).then(|| vec![(0, text.len())]),
+            NameMatcher::Regex(regex) => regex.is_match(text).then(|| {
+                regex
+                    .find_iter(text)
+                    .filter(|m| !m.is_empty())
+                    .map(|m| (m.start(), m.end()))
+                    .collect()
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_matchers() {
+        let literal = NameMatcher::literal("main", true);
+        assert_eq!(literal.find("Main.rs"), Some(vec![(0, 4)]));
+        assert_eq!(literal.find("lib.rs"), None);
+
+        let glob = NameMatcher::glob("*.rs", false).unwrap();
+        assert_eq!(glob.find("main.rs"), Some(vec![(0, 7)]));
+        assert_eq!(glob.find("src/main.rs"), None);
+
+        let regex = NameMatcher::regex(r"_test\.rs$", false).unwrap();
+        assert_eq!(regex.find("parser_test.rs"), Some(vec![(6, 14)]));
+        assert_eq!(regex.find("parser.rs"), None);
+    }
+}
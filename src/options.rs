@@ -0,0 +1,153 @@
+use clap::ValueEnum;
+
+use crate::archive::ArchiveOptions;
+use crate::format::DEFAULT_CONTEXT_CHARS;
+use crate::template::OutputTemplates;
+use crate::walk::WalkOptions;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// How the results of a `find` run are reported
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Print every matching line (the default)
+    #[default]
+    Lines,
+    /// Print the number of matching lines per file
+    Count,
+    /// Print the number of match occurrences per file
+    CountMatches,
+    /// Print only the paths of files containing a match
+    FilesWithMatches,
+    /// Print only the paths of files without any match
+    FilesWithoutMatch,
+    /// Print nothing; only the exit status tells whether anything matched
+    Quiet,
+}
+
+impl OutputMode {
+    /// Whether the first match already decides the outcome for a file
+    pub fn stops_at_first_match(&self) -> bool {
+        matches!(
+            self,
+            OutputMode::FilesWithMatches | OutputMode::FilesWithoutMatch | OutputMode::Quiet
+        )
+    }
+
+    /// Whether a file with (or without) matches is part of the results
+    pub fn reports_file(&self, has_match: bool) -> bool {
+        has_match != (*self == OutputMode::FilesWithoutMatch)
+    }
+}
+
+/// How matching lines are laid out in the default (lines) mode
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineLayout {
+    /// A header per file and a description of every line (the default)
+    #[default]
+    Verbose,
+    /// The path once per file, then `line:text`
+    Heading,
+    /// `path:line:text` on every line
+    NoHeading,
+    /// `path:line:column:text` for every match occurrence
+    Vimgrep,
+}
+
+impl LineLayout {
+    /// Column unit used when `--column-unit` is not given. Vim's quickfix
+    /// list and `rg --vimgrep` count columns in bytes.
+    pub fn default_column_unit(&self) -> ColumnUnit {
+        match self {
+            LineLayout::Vimgrep => ColumnUnit::Byte,
+            _ => ColumnUnit::Char,
+        }
+    }
+}
+
+/// The unit in which match columns are reported
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColumnUnit {
+    /// UTF-8 bytes
+    Byte,
+    /// Unicode scalar values
+    #[default]
+    Char,
+    /// Extended grapheme clusters (user-perceived characters)
+    Grapheme,
+    /// Terminal display columns, counting East Asian wide characters as two
+    Width,
+}
+
+impl ColumnUnit {
+    /// Length of `text` measured in this unit
+    pub fn measure(&self, text: &str) -> usize {
+        match self {
+            ColumnUnit::Byte => text.len(),
+            ColumnUnit::Char => text.chars().count(),
+            ColumnUnit::Grapheme => text.graphemes(true).count(),
+            ColumnUnit::Width => text.width(),
+        }
+    }
+}
+
+/// How long lines are shortened in the terminal output
+#[derive(Clone, Copy, Debug)]
+pub struct WrapOptions {
+    /// Omit lines wider than this many terminal columns
+    pub max_columns: Option<usize>,
+    /// Show the matches of omitted lines, cut to `max_columns`
+    pub max_columns_preview: bool,
+    /// Print whole lines instead of fitting them to the terminal width
+    pub no_truncate: bool,
+    /// Columns of context kept around the matches when a line is cut
+    pub context_chars: usize,
+}
+
+impl Default for WrapOptions {
+    fn default() -> Self {
+        WrapOptions {
+            max_columns: None,
+            max_columns_preview: false,
+            no_truncate: false,
+            context_chars: DEFAULT_CONTEXT_CHARS,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct SearchOptions {
+    pub ignore_case: bool,
+    /// Ignore case only when the query contains no uppercase letter
+    pub smart_case: bool,
+    pub mode: OutputMode,
+    pub layout: LineLayout,
+    pub null_separator: bool,
+    pub invert_match: bool,
+    /// Only accept matches surrounded by non-word characters
+    pub word_regexp: bool,
+    /// Only accept matches spanning the whole line
+    pub line_regexp: bool,
+    pub column_unit: ColumnUnit,
+    pub wrap: WrapOptions,
+    /// Stop searching a file after this many reported lines
+    pub max_count: Option<usize>,
+    /// Stop the whole search after this many reported lines
+    pub max_total: Option<usize>,
+    pub walk: WalkOptions,
+    /// Transparently decompress gzip, bzip2, xz and zstd files
+    pub search_zip: bool,
+    /// Search the members of zip, jar and tar archives
+    pub search_archives: bool,
+    pub archive: ArchiveOptions,
+    /// Abort the search when a file cannot be read instead of skipping it
+    pub fail_on_read_error: bool,
+    /// User templates that replace the line layout
+    pub templates: Option<OutputTemplates>,
+    /// Never draw the progress bar
+    pub no_progress: bool,
+    /// Print search statistics to stderr when done
+    pub stats: bool,
+    /// Lines kept before and after every reported line (for HTML reports)
+    pub context_lines: usize,
+}
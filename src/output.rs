@@ -0,0 +1,116 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use tempfile::NamedTempFile;
+
+use crate::report::OutputFormat;
+
+/// File name used when `--output` names a directory
+const DEFAULT_FILE_STEM: &str = "output";
+
+/// Expands the placeholders of an `--output` template. `{date}` and `{time}`
+/// are the local start time of the run, `{query}` the query with characters
+/// that are unsafe in file names replaced by `_`.
+pub fn expand_template(template: &str, query: &str, now: DateTime<Local>) -> String {
+    template
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{time}", &now.format("%H%M%S").to_string())
+        .replace("{query}", &file_name_safe(query))
+}
+
+fn file_name_safe(text: &str) -> String {
+    let safe: String = text
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    safe.trim_matches('.').to_string()
+}
+
+/// Resolves the `--output` argument to the file to write. An existing
+/// directory (or a path ending with a separator) receives `output.<ext>`.
+pub fn resolve_path(spec: &str, query: &str, format: OutputFormat) -> PathBuf {
+    let path = PathBuf::from(expand_template(spec, query, Local::now()));
+    if path.is_dir() || spec.ends_with(std::path::MAIN_SEPARATOR) || spec.ends_with('/') {
+        path.join(format!("{}.{}", DEFAULT_FILE_STEM, format.extension()))
+    } else {
+        path
+    }
+}
+
+/// Writes `content` to `path` through a temporary file in the same directory
+/// that is renamed over the target, so readers never see a partial report.
+/// With `append` the previous content of the file is kept in front.
+pub fn write_atomic(path: &Path, content: &str, append: bool) -> Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir).with_context(|| format!("Cannot create directory '{}'", dir.display()))?;
+
+    let mut temp = NamedTempFile::new_in(dir)?;
+    let existing = fs::File::open(path).ok();
+    if append && let Some(mut file) = existing.as_ref() {
+        io::copy(&mut file, &mut temp)?;
+    }
+    temp.write_all(content.as_bytes())?;
+    temp.as_file().sync_all()?;
+
+    // The temporary file is private; give the report the permissions of the
+    // file it replaces, or the usual permissions of a new file
+    let permissions = match &existing {
+        Some(file) => file.metadata()?.permissions(),
+        None => default_permissions(temp.as_file())?,
+    };
+    temp.as_file().set_permissions(permissions)?;
+
+    temp.persist(path)
+        .with_context(|| format!("Cannot write '{}'", path.display()))?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn default_permissions(_file: &fs::File) -> io::Result<fs::Permissions> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(fs::Permissions::from_mode(0o644))
+}
+
+#[cfg(not(unix))]
+fn default_permissions(file: &fs::File) -> io::Result<fs::Permissions> {
+    Ok(file.metadata()?.permissions())
+}
+
+/// Whether appending to `path` adds to an existing, non-empty file
+pub fn has_content(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.len() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_expand_template() {
+        let now = Local.with_ymd_and_hms(2025, 3, 9, 14, 5, 0).unwrap();
+        assert_eq!(
+            expand_template("results-{date}-{query}.txt", "fn main()", now),
+            "results-2025-03-09-fn_main__.txt"
+        );
+        assert_eq!(expand_template("{time}/{query}.json", "../etc", now), "140500/_etc.json");
+    }
+
+    #[test]
+    fn test_write_atomic_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.txt");
+        write_atomic(&path, "first\n", true).unwrap();
+        write_atomic(&path, "second\n", true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+
+        write_atomic(&path, "third\n", false).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third\n");
+    }
+}
@@ -0,0 +1,122 @@
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::Result;
+use unicode_width::UnicodeWidthStr;
+
+/// Pager used when neither `$RSPFIND_PAGER` nor `$PAGER` is set
+const DEFAULT_PAGER: &str = "less -RFX";
+/// The default pager for `--pager`, which does not quit on short output
+const DEFAULT_PAGER_ALWAYS: &str = "less -RX";
+
+/// When terminal output goes through the pager
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Paging {
+    /// Page when stdout is a terminal and the output is taller than it
+    #[default]
+    Auto,
+    /// Page whenever stdout is a terminal, even when the output fits on one
+    /// screen
+    Always,
+    Never,
+}
+
+impl Paging {
+    /// Whether `text` should be shown through the pager
+    fn pages(&self, text: &str) -> bool {
+        if *self == Paging::Never || !io::stdout().is_terminal() || self.pager_command().is_none() {
+            return false;
+        }
+        match self {
+            Paging::Auto => match terminal_size::terminal_size() {
+                Some((terminal_size::Width(width), terminal_size::Height(height))) => {
+                    screen_rows(text, width as usize) > height as usize
+                }
+                None => false,
+            },
+            _ => true,
+        }
+    }
+
+    /// The pager command and its arguments; an empty variable turns paging off
+    fn pager_command(&self) -> Option<Vec<String>> {
+        let default = if *self == Paging::Always { DEFAULT_PAGER_ALWAYS } else { DEFAULT_PAGER };
+        let command = env::var("RSPFIND_PAGER")
+            .or_else(|_| env::var("PAGER"))
+            .unwrap_or_else(|_| default.to_string());
+        let args: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+        (!args.is_empty()).then_some(args)
+    }
+
+    /// Prints `text` to stdout, through the pager when this setting asks for
+    /// it. When the pager cannot be started the text is printed directly.
+    pub fn print(&self, text: &str) -> Result<()> {
+        if self.pages(text)
+            && let Some(args) = self.pager_command()
+        {
+            let mut command = Command::new(&args[0]);
+            command.args(&args[1..]).stdin(Stdio::piped());
+            // Like git, let `PAGER=less` show colors and quit on short output,
+            // except that --pager keeps short output on screen as well
+            if env::var_os("LESS").is_none() {
+                command.env("LESS", if *self == Paging::Always { "RX" } else { "FRX" });
+            }
+            if let Ok(mut child) = command.spawn() {
+                if let Some(mut stdin) = child.stdin.take() {
+                    // The pager closes its input when the user quits early
+                    ignore_broken_pipe(stdin.write_all(text.as_bytes()))?;
+                }
+                child.wait()?;
+                return Ok(());
+            }
+        }
+        let mut stdout = io::stdout().lock();
+        ignore_broken_pipe(stdout.write_all(text.as_bytes()).and_then(|_| stdout.flush()))?;
+        Ok(())
+    }
+}
+
+/// Number of terminal rows `text` takes, counting wrapped lines
+fn screen_rows(text: &str, width: usize) -> usize {
+    text.lines()
+        .map(|line| strip_ansi(line).width().div_ceil(width.max(1)).max(1))
+        .sum()
+}
+
+/// Removes the SGR color sequences (`ESC [ ... m`) the output contains
+fn strip_ansi(line: &str) -> String {
+    let mut plain = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+fn ignore_broken_pipe(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screen_rows() {
+        let text = format!("short\n\u{1b}[31m{}\u{1b}[0m\n\n中文中文\n", "x".repeat(25));
+        assert_eq!(screen_rows(&text, 10), 1 + 3 + 1 + 1);
+        assert_eq!(screen_rows(&text, 4), 2 + 7 + 1 + 2);
+    }
+}
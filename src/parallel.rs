@@ -1,48 +1,77 @@
+use std::collections::VecDeque;
 use std::fs;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use dashmap::DashMap;
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
-use walkdir::WalkDir;
 
+use crate::archive::ArchiveKind;
+use crate::decompress::{Compression, check_text};
 use crate::display::DisPlay;
 use crate::file::File;
+use crate::i18n::{tr, tr_with, warn};
+use crate::matcher::Matcher;
+use crate::options::{OutputMode, SearchOptions};
+use crate::stats::SearchStats;
+
+/// Path that stands for standard input
+pub const STDIN_PATH: &str = "-";
+/// Name under which matches from standard input are reported
+pub const STDIN_LABEL: &str = "<stdin>";
 
 pub struct ParallelProcessor {
-    query: Arc<String>,
+    matcher: Matcher,
+    options: SearchOptions,
     progress_bar: Option<ProgressBar>,
+    total_matches: AtomicUsize,
+    /// 启用 --fail-on-read-error 时记录第一个读取错误
+    read_error: OnceLock<String>,
+    stats: SearchStats,
 }
 
 impl ParallelProcessor {
-    pub fn new(query: String) -> Self {
+    pub fn new(query: String, options: SearchOptions) -> Self {
         Self {
-            query: Arc::new(query),
+            matcher: Matcher::new(&query, &options),
+            options,
             progress_bar: None,
+            total_matches: AtomicUsize::new(0),
+            read_error: OnceLock::new(),
+            stats: SearchStats::default(),
         }
     }
 
-    pub fn process_directory(&mut self, dir_path: PathBuf, ignore_case: bool) -> Result<DashMap<String, Vec<DisPlay>>> {
-        // 收集所有文件路径
-        let files: Vec<PathBuf> = WalkDir::new(dir_path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .map(|e| e.into_path())
-            .collect();
+    /// 搜索任意数量的文件和目录，所有文件在同一个并行流程中处理
+    pub fn process_paths(&mut self, paths: Vec<PathBuf>) -> Result<DashMap<String, Vec<DisPlay>>> {
+        let (files, filtered_out) = self.options.walk.collect_files(paths);
+        self.stats.skipped_filtered.store(filtered_out, Ordering::Relaxed);
+        self.process_files(files)
+    }
 
+    pub fn process_files(&mut self, files: Vec<PathBuf>) -> Result<DashMap<String, Vec<DisPlay>>> {
         if files.is_empty() {
             return Ok(DashMap::new());
         }
 
-        // 创建进度条
-        let progress_bar = ProgressBar::new(files.len() as u64);
+        self.stats.files_total.store(files.len(), Ordering::Relaxed);
+
+        // 创建进度条，输出到标准错误；安静模式、--no-progress 或标准错误不是终端时不显示
+        let hide_progress = self.options.mode == OutputMode::Quiet
+            || self.options.no_progress
+            || !io::stderr().is_terminal();
+        let progress_bar = if hide_progress {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new(files.len() as u64)
+        };
         progress_bar.set_style(
-            ProgressStyle::with_template(
-                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} files ({eta})",
-            )
+            ProgressStyle::with_template(tr("progress_template"))
             .unwrap()
             .progress_chars("#>-"),
         );
@@ -51,76 +80,196 @@ impl ParallelProcessor {
         // 并行处理文件
         let results: DashMap<String, Vec<DisPlay>> = DashMap::new();
         
-        files.par_iter().for_each(|file_path| {
-            if let Ok(content) = fs::read_to_string(file_path) {
-                let file_name = file_path.to_string_lossy().to_string();
-                let mut file = File::new(file_name.clone(), crate::file::Format::Text, content);
-                
-                if let Ok(display_list) = self.process_single_file(&mut file, ignore_case) {
-                    if !display_list.is_empty() {
-                        results.insert(file_name, display_list);
+        // 达到 --max-total 上限或遇到致命的读取错误后提前结束并行遍历
+        let _ = files.par_iter().try_for_each(|file_path| {
+            if self.total_limit_reached() || self.read_error.get().is_some() {
+                return None;
+            }
+            // 先计入已访问：--fail-on-read-error 提前返回时，该文件只算作无法读取
+            self.stats.files_visited.fetch_add(1, Ordering::Relaxed);
+
+            if let Some(kind) = self.archive_kind(file_path) {
+                // 归档中的每个文本成员都作为独立的文件搜索，成员已完整读入内存，搜索时不会出错
+                let visited = self.options.archive.for_each_member(file_path, kind, &mut |mut file| {
+                    if self.search_file(&mut file, &results).is_ok() {
+                        self.stats.members_searched.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+                match visited {
+                    Ok(()) => {
+                        self.stats.files_searched.fetch_add(1, Ordering::Relaxed);
                     }
+                    // 归档的错误都是损坏或无法读取，不能当作二进制文件跳过
+                    Err(err) => self.report_read_error(file_path, &io::Error::other(err))?,
+                }
+            } else {
+                match self
+                    .read_file(file_path)
+                    .and_then(|mut file| self.search_file(&mut file, &results))
+                {
+                    Ok(()) => {
+                        self.stats.files_searched.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(err) => self.report_read_error(file_path, &err)?,
                 }
             }
-            
+
             progress_bar.inc(1);
+            Some(())
         });
 
-        progress_bar.finish_with_message("并行搜索完成");
+        // 搜索结束后清除进度条，避免留在结果输出之前
+        progress_bar.finish_and_clear();
+        if let Some(message) = self.read_error.get() {
+            return Err(anyhow!("{}", message));
+        }
         Ok(results)
     }
 
-    fn process_single_file(&self, file: &mut File, ignore_case: bool) -> Result<Vec<DisPlay>> {
-        let mut display_list = Vec::new();
-
-        let query = &self.query;
-        let file_name = file.name.clone();
-        let query_str = query.as_str();
-        
-        let mut line_index = 0;
-        while let Some(line) = file.next_line() {
-            let ori_query = query_str.to_string();
-            let ori_line = line.clone();
-            // 如果忽略大小写，则转换为小写
-            let line = if ignore_case {
-                line.to_lowercase()
-            } else {
-                line.clone()
-            };
+    /// 搜索过程中收集的统计信息，供 --stats 输出
+    pub fn stats(&self) -> &SearchStats {
+        &self.stats
+    }
 
-            let query_str = if ignore_case {
-                query_str.to_lowercase()
-            } else {
-                query_str.to_string()
-            };
+    fn archive_kind(&self, file_path: &Path) -> Option<ArchiveKind> {
+        if !self.options.search_archives {
+            return None;
+        }
+        ArchiveKind::from_name(&file_path.to_string_lossy())
+    }
 
-            if !line.contains(&query_str) {
-                line_index += 1;
-                continue;
+    /// 搜索一个文件并记录结果；读取中途出错时保留已找到的匹配并返回该错误，
+    /// 由调用方报告
+    fn search_file(&self, file: &mut File, results: &DashMap<String, Vec<DisPlay>>) -> io::Result<()> {
+        let processed = self.process_single_file(file);
+        self.stats.bytes_read.fetch_add(file.bytes_read(), Ordering::Relaxed);
+        if let Ok(display_list) = processed {
+            // 列出无匹配文件时也需要记录空结果
+            if !display_list.is_empty() || self.options.mode == OutputMode::FilesWithoutMatch {
+                results.insert(file.name.clone(), display_list);
             }
+        }
+        match file.take_error() {
+            // 解压失败等错误可能带有 InvalidData，不能当作二进制文件跳过
+            Some(err) => Err(io::Error::other(err)),
+            None => Ok(()),
+        }
+    }
 
-            let mut match_indices = Vec::new();
-            let mut matched_str = "";
+    /// 读取待搜索的文件，`-` 表示标准输入
+    fn read_file(&self, file_path: &Path) -> io::Result<File> {
+        if file_path == Path::new(STDIN_PATH) {
+            let content = io::read_to_string(io::stdin())?;
+            return Ok(File::new(STDIN_LABEL.to_string(), content));
+        }
 
-            for (start, matched) in line.match_indices(&query_str) {
-                match_indices.push(start);
-                matched_str = matched;
+        // 压缩文件以流的方式解压，匹配结果仍然使用原始的压缩文件路径；
+        // 检测压缩格式时只预读文件开头，不会重复打开文件
+        let mut reader = BufReader::new(fs::File::open(file_path)?);
+        let file_name = file_path.to_string_lossy().to_string();
+        if self.options.search_zip
+            && let Some(compression) = Compression::detect(file_path, &mut reader)?
+        {
+            // 解压后的二进制内容同样跳过，只检查第一个数据块
+            let mut decoder = compression.decoder(reader)?;
+            check_text(&mut decoder)?;
+            return Ok(File::from_reader(file_name, decoder));
+        }
+
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+        Ok(File::new(file_name, content))
+    }
+
+    /// 处理无法读取的文件：默认给出警告后跳过，启用 --fail-on-read-error 时
+    /// 记录错误并返回 None 以停止搜索
+    fn report_read_error(&self, file_path: &Path, err: &io::Error) -> Option<()> {
+        // 非 UTF-8 内容说明是二进制文件，不算读取错误
+        if err.kind() == io::ErrorKind::InvalidData {
+            self.stats.skipped_binary.fetch_add(1, Ordering::Relaxed);
+            return Some(());
+        }
+        self.stats.skipped_unreadable.fetch_add(1, Ordering::Relaxed);
+
+        let message = tr_with("cannot_read", &[("path", &file_path.display()), ("error", err)]);
+        if self.options.fail_on_read_error {
+            let _ = self.read_error.set(message);
+            return None;
+        }
+        warn(&message);
+        Some(())
+    }
+
+    fn process_single_file(&self, file: &mut File) -> Result<Vec<DisPlay>> {
+        let mut display_list: Vec<DisPlay> = Vec::new();
+
+        // 上下文行：before 保存最近的未报告行，after_remaining 是上一个结果行之后还需收集的行数，
+        // 每一行最多属于一个结果行的上下文
+        let context_lines = self.options.context_lines;
+        let mut before: VecDeque<String> = VecDeque::with_capacity(context_lines);
+        let mut after_remaining = 0;
+
+        let mut line_index = 0;
+        while let Some(line) = file.next_line() {
+            // 每个文件最多报告 --max-count 行，之后只继续收集最后一行的上下文
+            let limit_reached = self
+                .options
+                .max_count
+                .is_some_and(|max_count| display_list.len() >= max_count);
+            if limit_reached && after_remaining == 0 {
+                break;
             }
 
-            if !match_indices.is_empty() {
-                let display = DisPlay::new(
-                    ori_query,
-                    file_name.clone(),
+            let match_indices = self.matcher.find_matches(line);
+
+            // 反向匹配时报告不包含查询内容的行
+            if !limit_reached && match_indices.is_empty() == self.options.invert_match {
+                if !self.reserve_total_match() {
+                    break;
+                }
+
+                let mut display = DisPlay::new(
                     line_index,
                     match_indices,
-                    ori_line,
-                    matched_str.to_string(),
+                    line.clone(),
+                    self.options.column_unit,
                 );
+                display.set_context_before(before.drain(..).collect());
                 display_list.push(display);
+                after_remaining = context_lines;
+
+                // 只需判断文件是否匹配时，找到第一个匹配即可结束
+                if self.options.mode.stops_at_first_match() {
+                    break;
+                }
+            } else if after_remaining > 0 {
+                if let Some(display) = display_list.last_mut() {
+                    display.push_context_after(line.clone());
+                }
+                after_remaining -= 1;
+            } else if context_lines > 0 {
+                if before.len() == context_lines {
+                    before.pop_front();
+                }
+                before.push_back(line.clone());
             }
             line_index += 1;
         }
 
         Ok(display_list)
     }
+
+    /// 为一行结果占用 --max-total 名额，名额用完时返回 false
+    fn reserve_total_match(&self) -> bool {
+        match self.options.max_total {
+            Some(max_total) => self.total_matches.fetch_add(1, Ordering::Relaxed) < max_total,
+            None => true,
+        }
+    }
+
+    fn total_limit_reached(&self) -> bool {
+        self.options
+            .max_total
+            .is_some_and(|max_total| self.total_matches.load(Ordering::Relaxed) >= max_total)
+    }
 }
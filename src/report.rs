@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::display::DisPlay;
+use crate::options::{ColumnUnit, SearchOptions};
+
+/// File formats a `find` report can be written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// The same layout as the terminal output, without colors
+    Text,
+    Json,
+    Csv,
+    /// Tab-separated values
+    Tsv,
+    Markdown,
+    Html,
+    /// SARIF 2.1.0, for code-scanning tools
+    Sarif,
+}
+
+impl OutputFormat {
+    /// Guesses the format from the extension of an output file
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "txt" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "csv" => Some(OutputFormat::Csv),
+            "tsv" | "tab" => Some(OutputFormat::Tsv),
+            "md" | "markdown" => Some(OutputFormat::Markdown),
+            "html" | "htm" => Some(OutputFormat::Html),
+            "sarif" => Some(OutputFormat::Sarif),
+            _ => None,
+        }
+    }
+
+    /// Extension used for default output file names
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Json => "json",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Markdown => "md",
+            OutputFormat::Html => "html",
+            OutputFormat::Sarif => "sarif",
+        }
+    }
+
+    /// Whether `--append` can add a report to an existing file of this
+    /// format; JSON, HTML and SARIF reports are complete documents
+    pub fn can_append(&self) -> bool {
+        !matches!(self, OutputFormat::Json | OutputFormat::Html | OutputFormat::Sarif)
+    }
+}
+
+/// Columns available in CSV and TSV reports
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CsvColumn {
+    Path,
+    /// 1-based line number
+    Line,
+    /// 1-based byte offset of the match in the line
+    ByteColumn,
+    /// 1-based character offset of the match in the line
+    CharColumn,
+    /// The matched text
+    Match,
+    /// The pattern that produced the match
+    Pattern,
+    /// The full line
+    Text,
+}
+
+impl CsvColumn {
+    pub const ALL: [CsvColumn; 7] = [
+        CsvColumn::Path,
+        CsvColumn::Line,
+        CsvColumn::ByteColumn,
+        CsvColumn::CharColumn,
+        CsvColumn::Match,
+        CsvColumn::Pattern,
+        CsvColumn::Text,
+    ];
+
+    fn header(&self) -> &'static str {
+        match self {
+            CsvColumn::Path => "path",
+            CsvColumn::Line => "line",
+            CsvColumn::ByteColumn => "byte_column",
+            CsvColumn::CharColumn => "char_column",
+            CsvColumn::Match => "match",
+            CsvColumn::Pattern => "pattern",
+            CsvColumn::Text => "text",
+        }
+    }
+}
+
+/// Layout of CSV and TSV reports
+#[derive(Clone)]
+pub struct CsvOptions {
+    /// Field delimiter; `None` uses `,` for CSV and a tab for TSV
+    pub delimiter: Option<char>,
+    pub columns: Vec<CsvColumn>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: None,
+            columns: CsvColumn::ALL.to_vec(),
+        }
+    }
+}
+
+/// One CSV record: a match occurrence, or a line or file without one
+struct CsvRecord<'a> {
+    path: &'a str,
+    display: Option<&'a DisPlay>,
+    span: Option<(usize, usize)>,
+}
+
+/// The reported files of a `find` run, sorted by path, ready to be rendered
+pub struct Report<'a> {
+    query: &'a str,
+    options: &'a SearchOptions,
+    files: Vec<(&'a str, &'a [DisPlay])>,
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    query: &'a str,
+    files: Vec<JsonFile<'a>>,
+    total_files: usize,
+    total_lines: usize,
+}
+
+#[derive(Serialize)]
+struct JsonFile<'a> {
+    path: &'a str,
+    matches: Vec<JsonMatch<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonMatch<'a> {
+    line: usize,
+    /// 1-based start and end column of every occurrence
+    columns: Vec<(usize, usize)>,
+    text: &'a str,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(query: &'a str, display_map: &'a HashMap<String, Vec<DisPlay>>, options: &'a SearchOptions) -> Self {
+        let mut files: Vec<(&str, &[DisPlay])> = display_map
+            .iter()
+            .filter(|(_, displays)| options.mode.reports_file(!displays.is_empty()))
+            .map(|(path, displays)| (path.as_str(), displays.as_slice()))
+            .collect();
+        files.sort_by(|a, b| a.0.cmp(b.0));
+        Report { query, options, files }
+    }
+
+    pub fn query(&self) -> &str {
+        self.query
+    }
+
+    pub fn options(&self) -> &SearchOptions {
+        self.options
+    }
+
+    /// The reported files and their lines, sorted by path
+    pub fn files(&self) -> &[(&'a str, &'a [DisPlay])] {
+        &self.files
+    }
+
+    pub fn total_lines(&self) -> usize {
+        self.files.iter().map(|(_, displays)| displays.len()).sum()
+    }
+
+    pub fn json(&self) -> String {
+        let report = JsonReport {
+            query: self.query,
+            files: self
+                .files
+                .iter()
+                .map(|&(path, displays)| JsonFile {
+                    path,
+                    matches: displays
+                        .iter()
+                        .map(|display| JsonMatch {
+                            line: display.line_number(),
+                            columns: display.columns(),
+                            text: display.line_content(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+            total_files: self.files.len(),
+            total_lines: self.total_lines(),
+        };
+        // Serializing plain strings and numbers cannot fail
+        let mut json = serde_json::to_string_pretty(&report).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+
+    /// One record per match occurrence, quoted as described in RFC 4180.
+    /// Inverted lines and files without match get a record with empty match
+    /// columns. The header is left out when appending to an existing file.
+    pub fn csv(&self, format: OutputFormat, csv_options: &CsvOptions, with_header: bool) -> String {
+        let delimiter = csv_options
+            .delimiter
+            .unwrap_or(if format == OutputFormat::Tsv { '\t' } else { ',' });
+        let write_row = |out: &mut String, fields: Vec<String>| {
+            let fields: Vec<String> = fields.iter().map(|field| csv_field(field, delimiter)).collect();
+            out.push_str(&fields.join(&delimiter.to_string()));
+            out.push_str("\r\n");
+        };
+
+        let mut csv = String::new();
+        if with_header {
+            let header = csv_options.columns.iter().map(|column| column.header().to_string()).collect();
+            write_row(&mut csv, header);
+        }
+        for record in self.csv_records() {
+            let fields = csv_options.columns.iter().map(|column| self.csv_value(&record, *column)).collect();
+            write_row(&mut csv, fields);
+        }
+        csv
+    }
+
+    fn csv_records(&self) -> Vec<CsvRecord<'a>> {
+        let mut records = Vec::new();
+        for &(path, displays) in &self.files {
+            if displays.is_empty() {
+                records.push(CsvRecord { path, display: None, span: None });
+            }
+            for display in displays {
+                if display.match_spans().is_empty() {
+                    records.push(CsvRecord { path, display: Some(display), span: None });
+                }
+                for &span in display.match_spans() {
+                    records.push(CsvRecord { path, display: Some(display), span: Some(span) });
+                }
+            }
+        }
+        records
+    }
+
+    fn csv_value(&self, record: &CsvRecord, column: CsvColumn) -> String {
+        let line = record.display.map(|display| display.line_content()).unwrap_or_default();
+        let offset = |unit: ColumnUnit| record.span.map(|(start, _)| (unit.measure(&line[..start]) + 1).to_string());
+        match column {
+            CsvColumn::Path => Some(record.path.to_string()),
+            CsvColumn::Line => record.display.map(|display| display.line_number().to_string()),
+            CsvColumn::ByteColumn => offset(ColumnUnit::Byte),
+            CsvColumn::CharColumn => offset(ColumnUnit::Char),
+            CsvColumn::Match => record.span.map(|(start, end)| line[start..end].to_string()),
+            CsvColumn::Pattern => Some(self.query.to_string()),
+            CsvColumn::Text => record.display.is_some().then(|| line.to_string()),
+        }
+        .unwrap_or_default()
+    }
+
+    pub fn markdown(&self) -> String {
+        let mut markdown = format!(
+            "# Search results for `{}`\n\nFound {} matching lines in {} files.\n",
+            self.query.replace('`', "'"),
+            self.total_lines(),
+            self.files.len()
+        );
+        for &(path, displays) in &self.files {
+            markdown.push_str(&format!("\n## {}\n\n", path));
+            if displays.is_empty() {
+                continue;
+            }
+            markdown.push_str("| Line | Column | Content |\n|---:|---:|---|\n");
+            for display in displays {
+                let column = display.columns().first().map(|&(start, _)| start).unwrap_or_default();
+                markdown.push_str(&format!(
+                    "| {} | {} | {} |\n",
+                    display.line_number(),
+                    column,
+                    markdown_cell(display.line_content())
+                ));
+            }
+        }
+        markdown
+    }
+}
+
+/// Quotes a CSV field when needed, as described in RFC 4180
+fn csv_field(value: &str, delimiter: char) -> String {
+    if value.contains([delimiter, '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn markdown_cell(value: &str) -> String {
+    format!("`{}`", value.replace('`', "'").replace('|', "\\|"))
+}
+
+/// Two matches of "ab" on line 3, after a wide character and a comma
+#[cfg(test)]
+pub(crate) fn sample_display(column_unit: ColumnUnit) -> DisPlay {
+    DisPlay::new(2, vec![(4, 6), (8, 10)], "字 ab, ab".to_string(), column_unit)
+}
+
+/// The matches of [`sample_display`] reported for a single file
+#[cfg(test)]
+pub(crate) fn sample_display_map(path: &str) -> HashMap<String, Vec<DisPlay>> {
+    HashMap::from([(path.to_string(), vec![sample_display(ColumnUnit::Char)])])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_quoting() {
+        assert_eq!(csv_field("plain", ','), "plain");
+        assert_eq!(csv_field("a,b", ','), "\"a,b\"");
+        assert_eq!(csv_field("a,b", '\t'), "a,b");
+        assert_eq!(csv_field("a\tb", '\t'), "\"a\tb\"");
+        assert_eq!(csv_field("say \"hi\"", ','), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_csv_columns() {
+        let display_map = sample_display_map("a.txt");
+        let options = SearchOptions::default();
+        let report = Report::new("ab", &display_map, &options);
+
+        let csv_options = CsvOptions {
+            delimiter: None,
+            columns: vec![CsvColumn::CharColumn, CsvColumn::ByteColumn, CsvColumn::Text],
+        };
+        // The comma only needs quoting when it is the delimiter
+        assert_eq!(
+            report.csv(OutputFormat::Csv, &csv_options, true),
+            "char_column,byte_column,text\r\n3,5,\"字 ab, ab\"\r\n7,9,\"字 ab, ab\"\r\n"
+        );
+        assert_eq!(
+            report.csv(OutputFormat::Tsv, &csv_options, false),
+            "3\t5\t字 ab, ab\r\n7\t9\t字 ab, ab\r\n"
+        );
+
+        let csv_options = CsvOptions {
+            delimiter: Some(';'),
+            columns: vec![CsvColumn::Path, CsvColumn::Line, CsvColumn::Match],
+        };
+        assert_eq!(
+            report.csv(OutputFormat::Csv, &csv_options, false),
+            "a.txt;3;ab\r\na.txt;3;ab\r\n"
+        );
+    }
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(OutputFormat::from_extension(Path::new("out/report.JSON")), Some(OutputFormat::Json));
+        assert_eq!(OutputFormat::from_extension(Path::new("results.md")), Some(OutputFormat::Markdown));
+        assert_eq!(OutputFormat::from_extension(Path::new("results")), None);
+    }
+
+    #[test]
+    fn test_can_append() {
+        assert!(OutputFormat::Csv.can_append());
+        assert!(OutputFormat::Markdown.can_append());
+        assert!(!OutputFormat::Json.can_append());
+        assert!(!OutputFormat::Sarif.can_append());
+    }
+}
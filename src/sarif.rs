@@ -0,0 +1,229 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use serde_json::{Value, json};
+
+use crate::display::DisPlay;
+use crate::options::ColumnUnit;
+use crate::report::Report;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+/// Base id that relative artifact URIs are resolved against
+const SOURCE_ROOT: &str = "SRCROOT";
+
+/// Severity of the results of a rule
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SarifLevel {
+    Error,
+    #[default]
+    Warning,
+    Note,
+}
+
+impl SarifLevel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SarifLevel::Error => "error",
+            SarifLevel::Warning => "warning",
+            SarifLevel::Note => "note",
+        }
+    }
+}
+
+/// The rule under which the matches of the query are reported
+#[derive(Clone, Default)]
+pub struct SarifOptions {
+    /// Rule id; by default derived from the query
+    pub rule_id: Option<String>,
+    pub level: SarifLevel,
+}
+
+/// Renders the report as a SARIF 2.1.0 log with one rule for the query and
+/// one result per match occurrence. Paths below the current directory are
+/// written relative to `SRCROOT`, so code-scanning tools can map them to the
+/// repository.
+pub fn render(report: &Report, sarif_options: &SarifOptions) -> String {
+    let root = env::current_dir().ok();
+    let rule_id = sarif_options
+        .rule_id
+        .clone()
+        .unwrap_or_else(|| format!("rspfind/{}", rule_slug(report.query())));
+
+    let mut results = Vec::new();
+    for &(path, displays) in report.files() {
+        let artifact = artifact_location(path, root.as_deref());
+        if displays.is_empty() {
+            results.push(result(&rule_id, sarif_options.level, report.query(), &artifact, None));
+        }
+        for display in displays {
+            if display.match_spans().is_empty() {
+                results.push(result(&rule_id, sarif_options.level, report.query(), &artifact, Some(line_region(display))));
+            }
+            for &span in display.match_spans() {
+                let region = match_region(display, span);
+                results.push(result(&rule_id, sarif_options.level, report.query(), &artifact, Some(region)));
+            }
+        }
+    }
+
+    let mut run = json!({
+        "tool": {
+            "driver": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+                "rules": [{
+                    "id": rule_id,
+                    "name": report.query(),
+                    "shortDescription": { "text": format!("Lines matching \"{}\"", report.query()) },
+                    "defaultConfiguration": { "level": sarif_options.level.as_str() },
+                }],
+            }
+        },
+        "invocations": [{
+            "executionSuccessful": true,
+            "commandLine": env::args().collect::<Vec<_>>().join(" "),
+        }],
+        "columnKind": "unicodeCodePoints",
+        "results": results,
+    });
+    if let Some(root) = &root {
+        run["originalUriBaseIds"] = json!({ SOURCE_ROOT: { "uri": directory_uri(root) } });
+    }
+
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [run],
+    });
+    let mut sarif = serde_json::to_string_pretty(&log).unwrap_or_default();
+    sarif.push('\n');
+    sarif
+}
+
+fn result(rule_id: &str, level: SarifLevel, query: &str, artifact: &Value, region: Option<Value>) -> Value {
+    let mut physical_location = json!({ "artifactLocation": artifact });
+    if let Some(region) = region {
+        physical_location["region"] = region;
+    }
+    json!({
+        "ruleId": rule_id,
+        "ruleIndex": 0,
+        "level": level.as_str(),
+        "message": { "text": format!("Found \"{}\"", query) },
+        "locations": [{ "physicalLocation": physical_location }],
+    })
+}
+
+/// Region of one match; SARIF end columns point just past the match
+fn match_region(display: &DisPlay, (start, end): (usize, usize)) -> Value {
+    let line = display.line_content();
+    let start_column = ColumnUnit::Char.measure(&line[..start]) + 1;
+    let end_column = start_column + ColumnUnit::Char.measure(&line[start..end]);
+    json!({
+        "startLine": display.line_number(),
+        "startColumn": start_column,
+        "endLine": display.line_number(),
+        "endColumn": end_column,
+        "snippet": { "text": line },
+    })
+}
+
+/// Region of a whole line, for inverted matches
+fn line_region(display: &DisPlay) -> Value {
+    json!({
+        "startLine": display.line_number(),
+        "snippet": { "text": display.line_content() },
+    })
+}
+
+fn artifact_location(path: &str, root: Option<&Path>) -> Value {
+    let relative = root.and_then(|root| Path::new(path).strip_prefix(root).ok());
+    match relative {
+        Some(relative) => json!({
+            "uri": encode_uri_path(&relative.to_string_lossy()),
+            "uriBaseId": SOURCE_ROOT,
+        }),
+        None => json!({ "uri": file_uri(Path::new(path)) }),
+    }
+}
+
+fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    if path.starts_with('/') {
+        format!("file://{}", encode_uri_path(&path))
+    } else {
+        format!("file:///{}", encode_uri_path(&path))
+    }
+}
+
+fn directory_uri(dir: &Path) -> String {
+    let mut uri = file_uri(&PathBuf::from(dir));
+    if !uri.ends_with('/') {
+        uri.push('/');
+    }
+    uri
+}
+
+/// Percent-encodes everything but unreserved characters and `/`
+fn encode_uri_path(path: &str) -> String {
+    let mut encoded = String::new();
+    for byte in path.replace('\\', "/").bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn rule_slug(query: &str) -> String {
+    let slug: String = query
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() { "query".to_string() } else { slug.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::SearchOptions;
+    use crate::report::sample_display_map;
+
+    #[test]
+    fn test_sarif_regions() {
+        let root = env::current_dir().unwrap();
+        let path = root.join("src/lib.rs").to_string_lossy().to_string();
+        let display_map = sample_display_map(&path);
+        let options = SearchOptions::default();
+        let report = Report::new("ab", &display_map, &options);
+        let sarif_options = SarifOptions {
+            rule_id: None,
+            level: SarifLevel::Error,
+        };
+
+        let log: Value = serde_json::from_str(&render(&report, &sarif_options)).unwrap();
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "rspfind/ab");
+        assert_eq!(run["results"].as_array().unwrap().len(), 2);
+        let result = &run["results"][1];
+        assert_eq!(result["level"], "error");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/lib.rs");
+        assert_eq!(location["artifactLocation"]["uriBaseId"], "SRCROOT");
+        // Columns count characters, so the wide character is one column
+        assert_eq!(location["region"]["startLine"], 3);
+        assert_eq!(location["region"]["startColumn"], 7);
+        assert_eq!(location["region"]["endColumn"], 9);
+    }
+
+    #[test]
+    fn test_encode_uri_path() {
+        assert_eq!(encode_uri_path("docs/my notes#1.md"), "docs/my%20notes%231.md");
+        assert_eq!(file_uri(Path::new("/tmp/a b")), "file:///tmp/a%20b");
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::i18n::{tr, tr_with};
+
+/// Counters collected while searching, printed by `--stats`
+#[derive(Default)]
+pub struct SearchStats {
+    /// Files found by the walk, before reading them
+    pub files_total: AtomicUsize,
+    /// Files the search got to (searched or skipped)
+    pub files_visited: AtomicUsize,
+    /// Files whose content was searched, counting an archive once
+    pub files_searched: AtomicUsize,
+    /// Text members searched inside archives
+    pub members_searched: AtomicUsize,
+    pub bytes_read: AtomicU64,
+    /// Files excluded by the size, time, owner and permission filters
+    pub skipped_filtered: AtomicUsize,
+    pub skipped_binary: AtomicUsize,
+    pub skipped_unreadable: AtomicUsize,
+}
+
+/// What the search reported, counted from its results
+pub struct ResultCounts {
+    pub files: usize,
+    pub lines: usize,
+    pub matches: usize,
+}
+
+impl SearchStats {
+    /// The `--stats` summary, one `name: value` line per statistic, in the
+    /// language of this run
+    pub fn summary(&self, results: &ResultCounts, elapsed: Duration) -> String {
+        let load = |counter: &AtomicUsize| counter.load(Ordering::Relaxed);
+        let bytes_read = self.bytes_read.load(Ordering::Relaxed);
+        let filtered = load(&self.skipped_filtered);
+        let binary = load(&self.skipped_binary);
+        let unreadable = load(&self.skipped_unreadable);
+        // Files left when --max-total, --quiet or a read error ended the search early
+        let not_reached = load(&self.files_total).saturating_sub(load(&self.files_visited));
+        let seconds = elapsed.as_secs_f64();
+        let throughput = if seconds > 0.0 { (bytes_read as f64 / seconds) as u64 } else { 0 };
+        let searched = load(&self.files_searched);
+        let members = load(&self.members_searched);
+        // Members are listed apart so that searched and skipped files add up to the walked ones
+        let searched = if members > 0 {
+            tr_with("stats_searched_with_members", &[("files", &searched), ("members", &members)])
+        } else {
+            searched.to_string()
+        };
+
+        let rows = [
+            (tr("stats_files_searched"), searched),
+            (tr("stats_bytes_read"), format_bytes(bytes_read)),
+            (
+                tr("stats_files_skipped"),
+                tr_with(
+                    "stats_skipped_reasons",
+                    &[
+                        ("total", &(filtered + binary + unreadable + not_reached)),
+                        ("filtered", &filtered),
+                        ("binary", &binary),
+                        ("unreadable", &unreadable),
+                        ("not_searched", &not_reached),
+                    ],
+                ),
+            ),
+            (tr("stats_files_matched"), results.files.to_string()),
+            (tr("stats_matching_lines"), results.lines.to_string()),
+            (tr("stats_matches"), results.matches.to_string()),
+            (tr("stats_elapsed"), format!("{:.3}s", seconds)),
+            (tr("stats_throughput"), format!("{}/s", format_bytes(throughput))),
+        ];
+        // Labels are padded by display width so that translated labels line up too
+        let label_width = rows.iter().map(|(label, _)| label.width()).max().unwrap_or(0) + 1;
+        rows.iter()
+            .map(|(label, value)| format!("{}{}{}\n", label, " ".repeat(label_width - label.width()), value))
+            .collect()
+    }
+}
+
+/// Formats a byte count with binary units, e.g. `1.5 MiB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_summary() {
+        let stats = SearchStats::default();
+        stats.files_total.store(10, Ordering::Relaxed);
+        stats.files_visited.store(8, Ordering::Relaxed);
+        stats.files_searched.store(6, Ordering::Relaxed);
+        stats.skipped_filtered.store(3, Ordering::Relaxed);
+        stats.skipped_binary.store(1, Ordering::Relaxed);
+        stats.skipped_unreadable.store(1, Ordering::Relaxed);
+        stats.bytes_read.store(3 * 1024 * 1024, Ordering::Relaxed);
+        let results = ResultCounts {
+            files: 2,
+            lines: 5,
+            matches: 7,
+        };
+
+        let summary = stats.summary(&results, Duration::from_millis(1500));
+        assert!(summary.contains("Files searched: 6\n"));
+        assert!(summary.contains("Bytes read:     3.0 MiB\n"));
+        assert!(summary.contains(
+            "Files skipped:  7 (3 filtered out, 1 binary, 1 unreadable, 2 not searched (stopped early))\n"
+        ));
+        assert!(summary.contains("Elapsed:        1.500s\n"));
+        assert!(summary.contains("Throughput:     2.0 MiB/s\n"));
+
+        stats.members_searched.store(12, Ordering::Relaxed);
+        let summary = stats.summary(&results, Duration::from_millis(1500));
+        assert!(summary.contains("Files searched: 6 (and 12 archive members)\n"));
+        assert_eq!(format_bytes(512), "512 B");
+    }
+}
@@ -0,0 +1,322 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use owo_colors::{DynColors, OwoColorize, Style};
+
+use crate::report::Report;
+
+/// A value that can be inserted into an output template
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    /// Path of the file
+    Path,
+    /// Last component of the path
+    FileName,
+    /// 1-based line number
+    Line,
+    /// 1-based column of the match, in the `--column-unit`
+    Col,
+    /// The matched text
+    Match,
+    /// The text on the line before the match
+    Before,
+    /// The text on the line after the match
+    After,
+    /// The full line
+    LineText,
+    /// The query
+    Pattern,
+    /// Number of reported files (run header and footer)
+    Files,
+    /// Number of reported lines in the file or the run
+    Lines,
+    /// Number of match occurrences in the file or the run
+    Matches,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        let field = match name {
+            "path" => Field::Path,
+            "file_name" => Field::FileName,
+            "line" => Field::Line,
+            "col" => Field::Col,
+            "match" => Field::Match,
+            "before" => Field::Before,
+            "after" => Field::After,
+            "line_text" => Field::LineText,
+            "pattern" => Field::Pattern,
+            "files" => Field::Files,
+            "lines" => Field::Lines,
+            "matches" => Field::Matches,
+            _ => return None,
+        };
+        Some(field)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Literal(String),
+    Field(Field, Option<Style>),
+}
+
+/// A `--format` template such as `{path}:{line}: {match:bold,red}`.
+///
+/// `{name}` inserts a field and `{name:style,...}` also styles it with colors
+/// (`red`, `bright_blue`, `#ff8800`), background colors (`on_yellow`) and
+/// effects (`bold`, `dimmed`, `italic`, `underline`). `{{` and `}}` are
+/// literal braces, `\n` and `\t` a newline and a tab.
+#[derive(Clone, Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.char_indices().peekable();
+        while let Some((position, c)) = chars.next() {
+            let next = chars.peek().map(|&(_, next)| next);
+            match c {
+                '{' if next == Some('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if next == Some('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '\\' if next == Some('n') => {
+                    chars.next();
+                    literal.push('\n');
+                }
+                '\\' if next == Some('t') => {
+                    chars.next();
+                    literal.push('\t');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '}')) => break,
+                            Some((_, c)) => placeholder.push(c),
+                            None => return Err(format!("unterminated placeholder at byte {}", position)),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_placeholder(&placeholder)?);
+                }
+                '}' => return Err("unmatched '}' (write '}}' for a literal brace)".to_string()),
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template { parts })
+    }
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Part, String> {
+    let (name, styles) = match placeholder.split_once(':') {
+        Some((name, styles)) => (name, Some(styles)),
+        None => (placeholder, None),
+    };
+    let field = Field::from_name(name.trim()).ok_or_else(|| format!("unknown placeholder '{{{}}}'", name))?;
+    let style = styles.map(parse_style).transpose()?;
+    Ok(Part::Field(field, style))
+}
+
+/// Parses a comma-separated list of colors and effects
+pub fn parse_style(spec: &str) -> Result<Style, String> {
+    let mut style = Style::new();
+    for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        style = match item {
+            "bold" => style.bold(),
+            "dimmed" => style.dimmed(),
+            "italic" => style.italic(),
+            "underline" => style.underline(),
+            _ => match item.strip_prefix("on_") {
+                Some(color) => style.on_color(parse_color(color)?),
+                None => style.color(parse_color(item)?),
+            },
+        };
+    }
+    Ok(style)
+}
+
+pub fn parse_color(name: &str) -> Result<DynColors, String> {
+    name.replace('_', " ")
+        .parse()
+        .map_err(|_| format!("unknown color '{}'", name))
+}
+
+impl Template {
+    /// Fills in the template. Fields `value` does not know are left empty,
+    /// and styles are dropped when `color` is false.
+    pub fn render(&self, value: &dyn Fn(Field) -> Option<String>, color: bool) -> String {
+        let mut output = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => output.push_str(text),
+                Part::Field(field, style) => {
+                    let text = value(*field).unwrap_or_default();
+                    match style {
+                        Some(style) if color && !text.is_empty() => output.push_str(&text.style(*style).to_string()),
+                        _ => output.push_str(&text),
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+/// The templates given by `--format` and the header and footer options
+#[derive(Clone, Debug)]
+pub struct OutputTemplates {
+    /// Printed for every match occurrence
+    pub line: Template,
+    pub file_header: Option<Template>,
+    pub file_footer: Option<Template>,
+    /// Printed once before all files
+    pub header: Option<Template>,
+    /// Printed once after all files
+    pub footer: Option<Template>,
+}
+
+/// Renders the report with the user's templates, each followed by a newline.
+/// Inverted lines have no occurrence and are rendered once with an empty
+/// `{match}`, column 1 and the whole line in `{after}`.
+pub fn render(report: &Report, templates: &OutputTemplates, color: bool) -> String {
+    let column_unit = report.options().column_unit;
+    let total_lines = report.total_lines();
+    let total_matches: usize = report
+        .files()
+        .iter()
+        .flat_map(|(_, displays)| displays.iter())
+        .map(|display| display.match_count())
+        .sum();
+    let run_value = |field: Field| match field {
+        Field::Pattern => Some(report.query().to_string()),
+        Field::Files => Some(report.files().len().to_string()),
+        Field::Lines => Some(total_lines.to_string()),
+        Field::Matches => Some(total_matches.to_string()),
+        _ => None,
+    };
+
+    let mut output = String::new();
+    let mut push = |template: &Template, value: &dyn Fn(Field) -> Option<String>| {
+        output.push_str(&template.render(value, color));
+        output.push('\n');
+    };
+
+    if let Some(header) = &templates.header {
+        push(header, &run_value);
+    }
+    for &(path, displays) in report.files() {
+        let file_value = |field: Field| match field {
+            Field::Path => Some(path.to_string()),
+            Field::FileName => Path::new(path).file_name().map(|name| name.to_string_lossy().to_string()),
+            Field::Lines => Some(displays.len().to_string()),
+            Field::Matches => Some(displays.iter().map(|display| display.match_count()).sum::<usize>().to_string()),
+            _ => run_value(field),
+        };
+        if let Some(file_header) = &templates.file_header {
+            push(file_header, &file_value);
+        }
+        for display in displays {
+            let line = display.line_content();
+            let spans = match display.match_spans() {
+                [] => vec![None],
+                spans => spans.iter().copied().map(Some).collect(),
+            };
+            for span in spans {
+                let (start, end) = span.unwrap_or((0, 0));
+                let match_value = |field: Field| match field {
+                    Field::Line => Some(display.line_number().to_string()),
+                    Field::Col => Some((column_unit.measure(&line[..start]) + 1).to_string()),
+                    Field::Match => Some(line[start..end].to_string()),
+                    Field::Before => Some(line[..start].to_string()),
+                    Field::After => Some(line[end..].to_string()),
+                    Field::LineText => Some(line.to_string()),
+                    _ => file_value(field),
+                };
+                push(&templates.line, &match_value);
+            }
+        }
+        if let Some(file_footer) = &templates.file_footer {
+            push(file_footer, &file_value);
+        }
+    }
+    if let Some(footer) = &templates.footer {
+        push(footer, &run_value);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::SearchOptions;
+    use crate::report::sample_display_map;
+
+    fn value(field: Field) -> Option<String> {
+        match field {
+            Field::Path => Some("src/main.rs".to_string()),
+            Field::Line => Some("7".to_string()),
+            Field::Match => Some("TODO".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        let template: Template = "{path}:{line}:\\t{{{match}}} {after}".parse().unwrap();
+        assert_eq!(template.render(&value, false), "src/main.rs:7:\t{TODO} ");
+
+        let styled: Template = "{match:bold,red,on_yellow}".parse().unwrap();
+        assert_eq!(styled.render(&value, false), "TODO");
+        assert_eq!(styled.render(&value, true), "\u{1b}[31;43;1mTODO\u{1b}[0m");
+    }
+
+    #[test]
+    fn test_render_report() {
+        let display_map = sample_display_map("src/x.rs");
+        let options = SearchOptions::default();
+        let report = Report::new("ab", &display_map, &options);
+        let templates = OutputTemplates {
+            line: "{file_name}:{line}:{col}: [{before}|{match}|{after}]".parse().unwrap(),
+            file_header: Some("== {path} ({matches})".parse().unwrap()),
+            file_footer: None,
+            header: None,
+            footer: Some("{matches} matches of {pattern} in {files} files".parse().unwrap()),
+        };
+        assert_eq!(
+            render(&report, &templates, false),
+            "== src/x.rs (2)\nx.rs:3:3: [字 |ab|, ab]\nx.rs:3:7: [字 ab, |ab|]\n2 matches of ab in 1 files\n"
+        );
+    }
+
+    #[test]
+    fn test_template_errors() {
+        assert!("{nope}".parse::<Template>().is_err());
+        assert!("{match:sparkly}".parse::<Template>().is_err());
+        assert!("a } b".parse::<Template>().is_err());
+        assert_eq!(
+            "{path:{line".parse::<Template>().unwrap_err(),
+            "unterminated placeholder at byte 0"
+        );
+        assert_eq!(
+            "{path}:{line".parse::<Template>().unwrap_err(),
+            "unterminated placeholder at byte 7"
+        );
+        assert!("{match:bright_magenta}".parse::<Template>().is_ok());
+    }
+}
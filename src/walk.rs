@@ -0,0 +1,118 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::filter::FileFilter;
+use crate::i18n::{tr_with, warn};
+use crate::parallel::STDIN_PATH;
+
+/// Directory traversal settings shared by content and file name searches
+#[derive(Clone, Default)]
+pub struct WalkOptions {
+    /// Do not descend more than this many levels below a searched directory
+    pub max_depth: Option<usize>,
+    /// Skip files fewer than this many levels below a searched directory
+    pub min_depth: Option<usize>,
+    pub follow_links: bool,
+    /// Do not cross into other filesystems (mount points)
+    pub one_file_system: bool,
+    pub filter: FileFilter,
+}
+
+impl WalkOptions {
+    /// 展开目录并过滤文件，重复的文件只保留一次；同时返回被元数据过滤排除的文件数。
+    /// 直接指定的文件可能也在另一个指定的目录中，因此统一在这里去重
+    pub fn collect_files(&self, paths: Vec<PathBuf>) -> (Vec<PathBuf>, usize) {
+        let mut seen = HashSet::new();
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut filtered_out = 0;
+        for path in paths {
+            let candidates = if path.is_dir() {
+                self.walk_directory(&path)
+            } else {
+                vec![path]
+            };
+            for file in candidates {
+                // 跟随符号链接时同一文件可能经由多条路径出现
+                let key = if self.follow_links {
+                    fs::canonicalize(&file).unwrap_or_else(|_| file.clone())
+                } else {
+                    file.clone()
+                };
+                if !seen.insert(key) {
+                    continue;
+                }
+                // 在读取文件之前根据元数据过滤
+                if self.passes_filter(&file) {
+                    files.push(file);
+                } else {
+                    filtered_out += 1;
+                }
+            }
+        }
+        (files, filtered_out)
+    }
+
+    /// 按深度、符号链接和文件系统边界选项遍历目录，返回其中的文件
+    fn walk_directory(&self, dir_path: &Path) -> Vec<PathBuf> {
+        let mut walker = WalkDir::new(dir_path)
+            .follow_links(self.follow_links)
+            .same_file_system(self.one_file_system);
+        if let Some(max_depth) = self.max_depth {
+            walker = walker.max_depth(max_depth);
+        }
+        if let Some(min_depth) = self.min_depth {
+            walker = walker.min_depth(min_depth);
+        }
+
+        walker
+            .into_iter()
+            .filter_map(|e| match e {
+                Ok(entry) => Some(entry),
+                Err(err) => {
+                    // 跟随符号链接时跳过形成环的目录
+                    if let (Some(ancestor), Some(path)) = (err.loop_ancestor(), err.path()) {
+                        warn(&tr_with(
+                            "symlink_loop",
+                            &[("path", &path.display()), ("ancestor", &ancestor.display())],
+                        ));
+                    }
+                    None
+                }
+            })
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+            .collect()
+    }
+
+    /// 对文件应用元数据过滤，标准输入不受影响
+    fn passes_filter(&self, path: &Path) -> bool {
+        path == Path::new(STDIN_PATH) || fs::metadata(path).is_ok_and(|m| self.filter.matches(&m))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_nested_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("sub/deep.txt");
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("top.txt"), "x").unwrap();
+        fs::write(&nested, "x").unwrap();
+
+        // 深度限制使遍历到不了的文件，直接指定时仍然搜索
+        let options = WalkOptions { max_depth: Some(1), ..Default::default() };
+        let (files, _) = options.collect_files(vec![dir.path().to_path_buf(), nested.clone()]);
+        assert_eq!(files, vec![dir.path().join("top.txt"), nested.clone()]);
+
+        // 遍历已经包含的文件只出现一次
+        let (files, _) = WalkOptions::default().collect_files(vec![dir.path().to_path_buf(), nested.clone()]);
+        assert_eq!(files.iter().filter(|file| **file == nested).count(), 1);
+        assert_eq!(files.len(), 2);
+    }
+}